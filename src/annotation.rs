@@ -0,0 +1,84 @@
+//! Grouping keys taken from interval annotations, such as ticket numbers.
+
+use crate::input::Interval;
+use regex::Regex;
+
+/// Which part of an annotation intervals are grouped by.
+#[derive(Debug, Clone)]
+pub enum AnnotationKey {
+    /// The whole annotation, trimmed.
+    Text,
+    /// The annotation's first word.
+    Word,
+    /// The first capture group of the first match, or the whole match for a
+    /// regex without groups.
+    Regex(Regex),
+}
+
+impl AnnotationKey {
+    /// Parses `text`, `word` or `/regex/`.
+    pub fn parse(value: &str) -> Result<AnnotationKey, String> {
+        match value {
+            "text" => Ok(AnnotationKey::Text),
+            "word" => Ok(AnnotationKey::Word),
+            _ => {
+                let regex = value
+                    .strip_prefix('/')
+                    .and_then(|value| value.strip_suffix('/'))
+                    .ok_or("expected 'text', 'word' or '/regex/'")?;
+                Regex::new(regex)
+                    .map(AnnotationKey::Regex)
+                    .map_err(|error| format!("invalid regex: {}", error))
+            }
+        }
+    }
+
+    /// The key for `annotation`; `None` when it is empty or the regex
+    /// doesn't match.
+    pub fn extract(&self, annotation: &str) -> Option<String> {
+        let key = match self {
+            AnnotationKey::Text => annotation.trim(),
+            AnnotationKey::Word => annotation.split_whitespace().next()?,
+            AnnotationKey::Regex(regex) => {
+                let captures = regex.captures(annotation)?;
+                captures.get(1).or_else(|| captures.get(0))?.as_str()
+            }
+        };
+        match key.is_empty() {
+            true => None,
+            false => Some(key.to_string()),
+        }
+    }
+}
+
+impl Interval {
+    /// The interval's annotation key; `None` without an annotation.
+    pub fn annotation_key(&self, key: &AnnotationKey) -> Option<String> {
+        key.extract(self.annotation.as_deref()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_text_words_and_regex_captures() {
+        let extract =
+            |key: &str, annotation: &str| AnnotationKey::parse(key).unwrap().extract(annotation);
+        assert_eq!(extract("text", "  fix parser "), Some("fix parser".into()));
+        assert_eq!(extract("text", "   "), None);
+        assert_eq!(
+            extract("word", "PROJ-12 fix parser"),
+            Some("PROJ-12".into())
+        );
+        assert_eq!(
+            extract("/[A-Z]+-\\d+/", "fix PROJ-12"),
+            Some("PROJ-12".into())
+        );
+        assert_eq!(extract("/#(\\d+)/", "see #42 and #43"), Some("42".into()));
+        assert_eq!(extract("/#(\\d+)/", "no ticket"), None);
+        assert!(AnnotationKey::parse("first").is_err());
+        assert!(AnnotationKey::parse("/(/").is_err());
+    }
+}
@@ -0,0 +1,367 @@
+//! Hourly rates, billing increments and amounts per report line.
+
+use crate::group::{group_contains, leaf_groups, GroupKey, Report};
+use crate::input::Data;
+use crate::options::Options;
+use crate::pattern::TagPattern;
+use chrono::Duration;
+
+/// Whether billing increments apply to each interval or to each group's
+/// total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Interval,
+    Group,
+}
+
+/// Billing configuration, from `reports.grouped.rate.*` and
+/// `reports.grouped.billing.*` settings.
+#[derive(Debug, Clone)]
+pub struct Billing {
+    /// Hourly rates per tag or tag prefix.
+    pub rates: Vec<(TagPattern, f64)>,
+    /// The rate for groups no pattern matches.
+    pub default_rate: Option<f64>,
+    /// Billable time is rounded up to a multiple of this.
+    pub increment: Option<Duration>,
+    pub rounding: Rounding,
+    pub currency: String,
+    /// Groups with a tag matching any of these are not billed.
+    pub exclude: Vec<TagPattern>,
+}
+
+impl Default for Billing {
+    fn default() -> Self {
+        Billing {
+            rates: vec![],
+            default_rate: None,
+            increment: None,
+            rounding: Rounding::Group,
+            currency: String::new(),
+            exclude: vec![],
+        }
+    }
+}
+
+/// The billing columns of one report line.
+#[derive(Debug, Clone)]
+pub struct BilledLine {
+    /// The line's time after rounding up to the increment.
+    pub billable: Duration,
+    pub rate: Option<f64>,
+    pub amount: f64,
+    /// Set for non-billable groups: excluded ones and those without a rate.
+    pub excluded: bool,
+}
+
+/// The billing columns for a whole [`Report`].
+#[derive(Debug)]
+pub struct Bill {
+    /// One entry per line of the report, in the same order.
+    pub lines: Vec<BilledLine>,
+    /// The groups the intervals are billed under, with their titles. Every
+    /// interval is billed under exactly one item, so the items add up to
+    /// the totals even when the report's lines overlap.
+    pub items: Vec<(String, BilledLine)>,
+    /// The billable time and amount of the non-excluded items.
+    pub billable: Duration,
+    pub amount: f64,
+}
+
+impl Billing {
+    /// The rate for a group with `tags`: the most specific matching rate
+    /// pattern wins, falling back to the default rate.
+    pub fn rate(&self, tags: &[String]) -> Option<f64> {
+        self.rates
+            .iter()
+            .filter(|(pattern, _)| tags.iter().any(|tag| pattern.matches(tag)))
+            .max_by_key(|(pattern, _)| pattern.specificity())
+            .map(|(_, rate)| *rate)
+            .or(self.default_rate)
+    }
+
+    pub fn is_excluded(&self, tags: &[String]) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| tags.iter().any(|tag| pattern.matches(tag)))
+    }
+
+    /// Rounds `duration` up to the billing increment.
+    pub fn round(&self, duration: Duration) -> Duration {
+        match self.increment {
+            Some(increment) if increment > Duration::zero() => {
+                let increments = (duration.num_seconds() + increment.num_seconds() - 1)
+                    / increment.num_seconds();
+                Duration::seconds(increments * increment.num_seconds())
+            }
+            _ => duration,
+        }
+    }
+
+    /// Bills a group with `tags` and the clipped `durations` of its
+    /// intervals, rounding each interval or their sum. `excluded` is set
+    /// when the intervals carry an excluded tag outside `tags`.
+    fn bill_group(&self, tags: &[String], excluded: bool, durations: &[Duration]) -> BilledLine {
+        let billable = match self.rounding {
+            Rounding::Group => self.round(
+                durations
+                    .iter()
+                    .fold(Duration::zero(), |sum, duration| sum + *duration),
+            ),
+            Rounding::Interval => durations.iter().fold(Duration::zero(), |sum, duration| {
+                sum + self.round(*duration)
+            }),
+        };
+        let rate = self.rate(tags);
+        let excluded = excluded || self.is_excluded(tags) || rate.is_none();
+        let amount = match excluded {
+            true => 0.0,
+            false => billable.num_seconds() as f64 / 3600.0 * rate.unwrap_or(0.0),
+        };
+        BilledLine {
+            billable,
+            rate,
+            amount,
+            excluded,
+        }
+    }
+
+    /// Of an interval's [`leaf_groups`], the one it is billed under: the
+    /// group with the tag the most specific rate pattern matches, or else
+    /// the first.
+    fn billed_group(&self, groups: Vec<GroupKey>) -> Option<GroupKey> {
+        let specificity = |group: &GroupKey| {
+            self.rates
+                .iter()
+                .filter(|(pattern, _)| group.tags.iter().any(|tag| pattern.matches(tag)))
+                .map(|(pattern, _)| pattern.specificity())
+                .max()
+        };
+        groups.into_iter().rev().max_by_key(specificity)
+    }
+
+    /// Bills every interval in `data` once, under the group
+    /// [`Billing::billed_group`] picks, at that group's rate. An interval
+    /// with an excluded tag is not billed at all and is listed under its
+    /// first group with an excluded tag instead. Every line of `report`, which must have been built from `data`
+    /// with the same `options`, adds up the items it contains, so lines of
+    /// overlapping groupings show only the time billed under them.
+    pub fn bill(&self, data: &Data, report: &Report, options: &Options) -> Bill {
+        let range = data.report_range();
+        // The clipped durations per billed group, with excluded intervals
+        // apart from the others.
+        let mut groups: Vec<(GroupKey, bool, Vec<Duration>)> = vec![];
+        for interval in &data.intervals {
+            if interval.clipped_bounds(&range).is_none() {
+                continue;
+            }
+            let leaves = leaf_groups(interval, options);
+            let tags: Vec<String> = leaves.iter().flat_map(|leaf| leaf.tags.clone()).collect();
+            let excluded = self.is_excluded(&tags);
+            let group = match excluded {
+                true => leaves.into_iter().find(|leaf| self.is_excluded(&leaf.tags)),
+                false => self.billed_group(leaves),
+            };
+            let Some(group) = group else {
+                continue;
+            };
+            let duration = interval.clipped_duration(&range);
+            match groups.iter_mut().find(|(billed, billed_excluded, _)| {
+                *billed == group && *billed_excluded == excluded
+            }) {
+                Some((_, _, durations)) => durations.push(duration),
+                None => groups.push((group, excluded, vec![duration])),
+            }
+        }
+        let items: Vec<(GroupKey, BilledLine)> = groups
+            .into_iter()
+            .map(|(group, excluded, durations)| {
+                let billed = self.bill_group(&group.tags, excluded, &durations);
+                (group, billed)
+            })
+            .collect();
+        let lines = report
+            .lines
+            .iter()
+            .map(|line| {
+                let key = line.row.key();
+                let contained: Vec<&BilledLine> = items
+                    .iter()
+                    .filter(|(group, _)| group_contains(&key, group, options))
+                    .map(|(_, billed)| billed)
+                    .collect();
+                let billed: Vec<&BilledLine> = contained
+                    .iter()
+                    .copied()
+                    .filter(|billed| !billed.excluded)
+                    .collect();
+                let rate = billed
+                    .first()
+                    .and_then(|first| first.rate)
+                    .filter(|rate| billed.iter().all(|billed| billed.rate == Some(*rate)));
+                BilledLine {
+                    billable: billed
+                        .iter()
+                        .fold(Duration::zero(), |sum, billed| sum + billed.billable),
+                    rate,
+                    amount: billed.iter().map(|billed| billed.amount).sum(),
+                    // A line whose time is all billed under other lines
+                    // is billed nothing rather than excluded.
+                    excluded: billed.is_empty() && !contained.is_empty(),
+                }
+            })
+            .collect();
+        let (billable, amount) = items.iter().filter(|(_, billed)| !billed.excluded).fold(
+            (Duration::zero(), 0.0),
+            |(billable, amount), (_, billed)| (billable + billed.billable, amount + billed.amount),
+        );
+        Bill {
+            lines,
+            items: items
+                .into_iter()
+                .map(|(group, billed)| (group.title(), billed))
+                .collect(),
+            billable,
+            amount,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::test_data;
+
+    const INTERVALS: [&str; 3] = [
+        "0800 - 0930 # client:acme:api code",
+        "0930 - 1000 # docs",
+        "1000 - 1030 # docs internal",
+    ];
+
+    /// Bills [`INTERVALS`] grouped by `grouping`.
+    fn bill(grouping: &str) -> (Report, Bill) {
+        let data = test_data(
+            &[
+                ("grouping", grouping),
+                ("rate", "100"),
+                ("rate.code", "50"),
+                ("rate.client:*", "120"),
+                ("billing.exclude", "internal"),
+            ],
+            &INTERVALS,
+        );
+        let options = Options::from_data(&data).unwrap();
+        let billing = options.billing.as_ref().unwrap();
+        let report = data.report(&options);
+        let bill = billing.bill(&data, &report, &options);
+        (report, bill)
+    }
+
+    fn items(bill: &Bill) -> Vec<(&str, i64, f64, bool)> {
+        bill.items
+            .iter()
+            .map(|(title, billed)| {
+                let minutes = billed.billable.num_minutes();
+                (title.as_str(), minutes, billed.amount, billed.excluded)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bills_each_interval_once_whatever_the_grouping() {
+        for grouping in ["combination", "unordered", "tag", "tree"] {
+            let (report, bill) = bill(grouping);
+            // The exact `code` rate beats the `client:*` prefix.
+            assert_eq!(bill.billable, Duration::minutes(120), "{}", grouping);
+            assert_eq!(bill.amount, 125.0, "{}", grouping);
+            assert!(bill.billable <= report.total);
+        }
+        assert_eq!(
+            items(&bill("tag").1),
+            [
+                ("code", 90, 75.0, false),
+                ("docs", 30, 50.0, false),
+                ("internal", 30, 0.0, true),
+            ]
+        );
+        assert_eq!(
+            items(&bill("combination").1),
+            [
+                ("client:acme:api, code", 90, 75.0, false),
+                ("docs", 30, 50.0, false),
+                ("docs, internal", 30, 0.0, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn adds_up_tree_lines_from_the_items_below_them() {
+        let (report, bill) = bill("tree");
+        let lines: Vec<(&str, i64, Option<f64>, bool)> = report
+            .lines
+            .iter()
+            .zip(&bill.lines)
+            .map(|(line, billed)| {
+                let minutes = billed.billable.num_minutes();
+                (
+                    line.row.title.as_str(),
+                    minutes,
+                    billed.rate,
+                    billed.excluded,
+                )
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                ("client", 0, None, false),
+                ("client:acme", 0, None, false),
+                ("client:acme:api", 0, None, false),
+                ("code", 90, Some(50.0), false),
+                ("docs", 30, Some(100.0), false),
+                ("internal", 0, None, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn rounds_intervals_or_groups_up_to_the_increment() {
+        let data = test_data(
+            &[("rate", "60"), ("billing.increment", "15min")],
+            &["0800 - 0805 # a", "0810 - 0820 # a"],
+        );
+        let options = Options::from_data(&data).unwrap();
+        let report = data.report(&options);
+        let mut billing = options.billing.clone().unwrap();
+        assert_eq!(
+            billing.bill(&data, &report, &options).billable,
+            Duration::minutes(15)
+        );
+        billing.rounding = Rounding::Interval;
+        let bill = billing.bill(&data, &report, &options);
+        assert_eq!(bill.billable, Duration::minutes(30));
+        assert_eq!(bill.amount, 30.0);
+    }
+
+    #[test]
+    fn bills_annotation_keys_apart_from_tags() {
+        let data = test_data(
+            &[("annotation", "text"), ("rate", "100")],
+            &["0800 - 0900 # # 42", "0900 - 0930 # 42"],
+        );
+        let options = Options::from_data(&data).unwrap();
+        let report = data.report(&options);
+        let bill = options
+            .billing
+            .as_ref()
+            .unwrap()
+            .bill(&data, &report, &options);
+        let lines: Vec<i64> = bill
+            .lines
+            .iter()
+            .map(|billed| billed.billable.num_minutes())
+            .collect();
+        assert_eq!(lines, [60, 30]);
+        assert_eq!(bill.billable, report.total);
+    }
+}
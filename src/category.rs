@@ -0,0 +1,53 @@
+//! Ordered rules that group intervals under named categories instead of
+//! their tags.
+
+use crate::input::Interval;
+use crate::pattern::TagFilter;
+
+/// One `reports.grouped.category.<N> = FILTER,... -> NAME` rule: intervals
+/// with a tag matching any of the filters are grouped under `name`.
+#[derive(Debug, Clone)]
+pub struct CategoryRule {
+    /// The `<N>` of the setting; rules are tried in ascending order.
+    pub number: u32,
+    pub filters: Vec<TagFilter>,
+    pub name: String,
+    /// The filters as written, for explaining matches.
+    pub source: String,
+}
+
+impl CategoryRule {
+    /// Parses a rule's value, `FILTER,... -> NAME`, where filters are tags,
+    /// globs or `/regexes/` as in [`TagFilter::parse`].
+    pub fn parse(number: u32, value: &str) -> Result<CategoryRule, String> {
+        let (filters, name) = value
+            .rsplit_once("->")
+            .ok_or("expected 'FILTER,... -> NAME'")?;
+        let (filters, name) = (filters.trim(), name.trim());
+        if name.is_empty() {
+            return Err(String::from("expected a category name after '->'"));
+        }
+        let parsed = TagFilter::parse_list(filters)?;
+        if parsed.is_empty() {
+            return Err(String::from("expected at least one tag filter before '->'"));
+        }
+        Ok(CategoryRule {
+            number,
+            filters: parsed,
+            name: name.to_string(),
+            source: filters.to_string(),
+        })
+    }
+
+    pub fn matches(&self, tags: &[String]) -> bool {
+        tags.iter()
+            .any(|tag| self.filters.iter().any(|filter| filter.matches(tag)))
+    }
+}
+
+impl Interval {
+    /// The first of `rules` that matches the interval's tags.
+    pub fn category<'a>(&self, rules: &'a [CategoryRule]) -> Option<&'a CategoryRule> {
+        rules.iter().find(|rule| rule.matches(&self.tags))
+    }
+}
@@ -0,0 +1,246 @@
+//! Comparing a report with the same report for the previous period.
+
+use crate::error::Error;
+use crate::group::Report;
+use crate::input::{read_data, Data, ReportRange};
+use crate::options::{Options, SETTINGS_PREFIX};
+use crate::timezone::ReportTimezone;
+use chrono::{Datelike, Duration, Months, NaiveTime};
+use std::io::BufRead;
+
+impl ReportRange {
+    /// The period of the same length just before this one, or `None` for an
+    /// open range. Whole calendar months step back by months and whole days
+    /// by days, both in local time so DST changes don't shift the bounds;
+    /// any other range steps back by its exact length.
+    pub fn previous(&self, timezone: &ReportTimezone) -> Option<ReportRange> {
+        let (start, end) = (self.start?, self.end?);
+        let (local_start, local_end) = (start.naive_local(), end.naive_local());
+        let midnight = NaiveTime::MIN;
+        if local_start.time() != midnight || local_end.time() != midnight {
+            let length = end - start;
+            return Some(ReportRange {
+                start: Some(start - length),
+                end: Some(start),
+                ..*self
+            });
+        }
+        let (start_date, end_date) = (local_start.date(), local_end.date());
+        let previous_start = match (start_date.day(), end_date.day()) {
+            (1, 1) => {
+                let months = (end_date.year() * 12 + end_date.month() as i32)
+                    - (start_date.year() * 12 + start_date.month() as i32);
+                start_date.checked_sub_months(Months::new(months.max(0) as u32))?
+            }
+            _ => start_date - (end_date - start_date),
+        };
+        Some(ReportRange {
+            start: Some(timezone.from_local(&previous_start.and_time(midnight))),
+            end: Some(start),
+            ..*self
+        })
+    }
+}
+
+/// The range of the period before `data`'s report range, or an error for
+/// an open range, which has no previous period.
+pub fn previous_range(data: &Data) -> Result<ReportRange, Error> {
+    data.report_range()
+        .previous(&data.timezone)
+        .ok_or_else(|| Error::Setting {
+            name: format!("{}compare", SETTINGS_PREFIX),
+            value: data
+                .find_setting(&format!("{}compare", SETTINGS_PREFIX))
+                .map(|value| value.0.clone())
+                .unwrap_or_default(),
+            message: String::from("comparing needs a report range with a start and an end"),
+        })
+}
+
+/// Reads the intervals for the period before `data`'s report range, in the
+/// extension format or as a bare JSON array like `timew export` prints.
+/// The reader's own range is replaced by [`ReportRange::previous`] and
+/// `data`'s `reports.grouped.*` settings apply to it, so both periods are
+/// grouped in the same timezone. Fails for an open report range.
+pub fn read_previous(data: &Data, reader: impl BufRead) -> Result<Data, Error> {
+    let overrides: Vec<(String, String)> = data
+        .settings
+        .iter()
+        .filter(|(key, _)| key.starts_with(SETTINGS_PREFIX))
+        .map(|(key, value)| (key.clone(), value.0.clone()))
+        .collect();
+    let range = previous_range(data)?;
+    let mut previous = read_data(reader, &overrides)?;
+    previous.range = range;
+    Ok(previous)
+}
+
+/// A duration next to the one for the previous period.
+#[derive(Debug, Clone, Copy)]
+pub struct Change {
+    pub previous: Duration,
+    pub delta: Duration,
+    /// The delta relative to the previous duration; `None` when that was
+    /// zero.
+    pub percent: Option<f64>,
+}
+
+impl Change {
+    pub fn new(current: Duration, previous: Duration) -> Change {
+        let delta = current - previous;
+        Change {
+            previous,
+            delta,
+            percent: match previous.num_seconds() {
+                0 => None,
+                seconds => Some(delta.num_seconds() as f64 / seconds as f64 * 100.0),
+            },
+        }
+    }
+}
+
+/// How every line of a report changed since the previous period.
+#[derive(Debug)]
+pub struct Comparison {
+    /// The previous period's range.
+    pub range: ReportRange,
+    /// One entry per line of the report, in the same order. Lines match
+    /// previous lines by their group; the report has a line for every
+    /// previous group, so the deltas add up like the durations.
+    pub lines: Vec<Change>,
+    pub total: Change,
+}
+
+impl Data {
+    /// Compares `report`, built from this data with `options`, with the
+    /// same report over [`Data::previous`]. `None` when there is nothing to
+    /// compare with.
+    pub fn compare(&self, report: &Report, options: &Options) -> Option<Comparison> {
+        let previous = self.previous.as_deref()?;
+        let previous_report = previous.report(options);
+        let lines = report
+            .lines
+            .iter()
+            .map(|line| {
+                let duration = previous_report
+                    .lines
+                    .iter()
+                    .find(|previous| previous.row.key() == line.row.key())
+                    .map_or(Duration::zero(), |previous| previous.row.duration);
+                Change::new(line.row.duration, duration)
+            })
+            .collect();
+        Some(Comparison {
+            range: previous.report_range(),
+            lines,
+            total: Change::new(report.total, previous_report.total),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::test_data;
+
+    fn compared(
+        settings: &[(&str, &str)],
+        current: &[&str],
+        previous: &[&str],
+    ) -> Vec<(String, i64, i64)> {
+        let mut data = test_data(settings, current);
+        let mut before = test_data(settings, previous);
+        before.range = previous_range(&data).unwrap();
+        data.previous = Some(Box::new(before));
+        let options = Options::from_data(&data).unwrap();
+        let report = data.report(&options);
+        let comparison = data.compare(&report, &options).unwrap();
+        let total = comparison
+            .lines
+            .iter()
+            .zip(&report.lines)
+            .filter(|(_, line)| line.depth == 0)
+            .fold(Duration::zero(), |sum, (change, _)| sum + change.delta);
+        assert_eq!(total, comparison.total.delta);
+        report
+            .lines
+            .iter()
+            .zip(&comparison.lines)
+            .map(|(line, change)| {
+                (
+                    line.row.title.clone(),
+                    line.row.duration.num_minutes(),
+                    change.previous.num_minutes(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn steps_back_by_days_months_or_the_exact_length() {
+        let previous = |start: &str, end: &str| {
+            let data = test_data(
+                &[("temp.report.start", start), ("temp.report.end", end)],
+                &[],
+            );
+            let range = previous_range(&data).unwrap();
+            (
+                range.start.unwrap().to_rfc3339(),
+                range.end.unwrap().to_rfc3339(),
+            )
+        };
+        assert_eq!(
+            previous("20231001T000000Z", "20231101T000000Z"),
+            (
+                "2023-09-01T00:00:00+00:00".into(),
+                "2023-10-01T00:00:00+00:00".into()
+            )
+        );
+        assert_eq!(
+            previous("20231009T000000Z", "20231016T000000Z"),
+            (
+                "2023-10-02T00:00:00+00:00".into(),
+                "2023-10-09T00:00:00+00:00".into()
+            )
+        );
+        assert_eq!(
+            previous("20231015T080000Z", "20231015T120000Z"),
+            (
+                "2023-10-15T04:00:00+00:00".into(),
+                "2023-10-15T08:00:00+00:00".into()
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_groups_only_the_previous_period_had() {
+        assert_eq!(
+            compared(
+                &[("grouping", "tag")],
+                &["0800 - 0900 # code"],
+                &[
+                    "1014T0800 - 1014T0830 # code",
+                    "1014T0900 - 1014T1100 # docs"
+                ],
+            ),
+            [("code".into(), 60, 30), ("docs".into(), 0, 120)]
+        );
+        assert_eq!(
+            compared(
+                &[("grouping", "tree")],
+                &["0800 - 0900 # client:acme:api"],
+                &[
+                    "1014T0800 - 1014T0900 # client:acme:web",
+                    "1014T0900 - 1014T0930 # code"
+                ],
+            ),
+            [
+                ("client".into(), 60, 60),
+                ("client:acme".into(), 60, 60),
+                ("client:acme:api".into(), 60, 0),
+                ("client:acme:web".into(), 0, 60),
+                ("code".into(), 0, 30),
+            ]
+        );
+    }
+}
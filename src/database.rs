@@ -0,0 +1,534 @@
+//! Reading timewarrior's database directory directly, so reports can run
+//! outside `timew`: the `data/YYYY-MM.data` interval files and
+//! `timewarrior.cfg`.
+
+use crate::compare::previous_range;
+use crate::error::Error;
+use crate::input::{Data, Interval, ReportRange, Value};
+use crate::options::SETTINGS_PREFIX;
+use crate::pivot::Period;
+use crate::timewarrior_datetime;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The database directory: `TIMEWARRIORDB`, or `~/.timewarrior`.
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("TIMEWARRIORDB")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".timewarrior")))
+}
+
+/// The database directory for `settings`: `reports.grouped.database` when
+/// it names a directory rather than turning the database on or off,
+/// `temp.db` when timewarrior passed one, or [`default_path`].
+pub fn path_for(settings: &HashMap<String, Value>) -> Option<PathBuf> {
+    let database = settings
+        .get(&format!("{}database", SETTINGS_PREFIX))
+        .map(|value| value.0.trim())
+        .filter(|value| !matches!(*value, "" | "on" | "yes" | "y" | "true" | "1" | "off"));
+    database
+        .map(PathBuf::from)
+        .or_else(|| {
+            settings
+                .get("temp.db")
+                .map(|value| PathBuf::from(value.0.trim()))
+        })
+        .or_else(default_path)
+}
+
+fn file_error(path: &Path) -> impl FnOnce(std::io::Error) -> Error + '_ {
+    move |source| Error::File {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Reads `timewarrior.cfg` as `name = value` settings. Nested blocks such as
+///
+/// ```text
+/// define reports:
+///   grouped:
+///     sort = title
+/// ```
+///
+/// are flattened to `reports.grouped.sort`. Files named by `import` lines
+/// are read in place; comments start with `#`.
+pub fn read_config(path: &Path) -> Result<Vec<(String, String)>, Error> {
+    let text = std::fs::read_to_string(path).map_err(file_error(path))?;
+    let mut settings = vec![];
+    // The names of the enclosing blocks with their indentation.
+    let mut blocks: Vec<(usize, String)> = vec![];
+    for line in text.lines() {
+        let line = match line.find('#') {
+            Some(index) => &line[..index],
+            None => line,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        while blocks.last().is_some_and(|(depth, _)| *depth >= indent) {
+            blocks.pop();
+        }
+        let line = line.trim();
+        let name = |key: &str| {
+            blocks
+                .iter()
+                .map(|(_, block)| block.as_str())
+                .chain([key.trim()])
+                .collect::<Vec<&str>>()
+                .join(".")
+        };
+        if let Some(import) = line.strip_prefix("import ") {
+            let import = match import.trim().strip_prefix("~/") {
+                Some(rest) => std::env::var_os("HOME")
+                    .map(|home| PathBuf::from(home).join(rest))
+                    .unwrap_or_else(|| PathBuf::from(import.trim())),
+                None => path.parent().unwrap_or(Path::new("")).join(import.trim()),
+            };
+            settings.extend(read_config(&import)?);
+        } else if let Some((key, value)) = line.split_once('=') {
+            settings.push((name(key), value.trim().to_string()));
+        } else if let Some(block) = line.strip_suffix(':') {
+            let block = block.strip_prefix("define ").unwrap_or(block);
+            blocks.push((indent, block.trim().to_string()));
+        } else if let Some((key, value)) = line.split_once(": ") {
+            settings.push((name(key), value.trim().to_string()));
+        }
+    }
+    Ok(settings)
+}
+
+/// Splits the rest of a data file line into words, unquoting `"..."` with
+/// backslash escapes. Each word comes with whether it was quoted.
+fn words(text: &str) -> Vec<(String, bool)> {
+    let mut words = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c == ' ' {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut word = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => word.extend(chars.next()),
+                    '"' => break,
+                    c => word.push(c),
+                }
+            }
+            words.push((word, true));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c == ' ' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            words.push((word, false));
+        }
+    }
+    words
+}
+
+/// Parses a data file line,
+/// `inc 20231015T080000Z - 20231015T093000Z # tag "two words" # "annotation"`.
+/// The end is missing for a running interval; tags and the annotation are
+/// optional.
+pub fn parse_interval(line: &str) -> Result<Interval, String> {
+    let rest = line
+        .strip_prefix("inc ")
+        .ok_or_else(|| String::from("expected an 'inc' line"))?;
+    let (times, rest) = match rest.split_once(" #") {
+        Some((times, rest)) => (times, rest),
+        None => (rest, ""),
+    };
+    let timestamp = |text: &str| {
+        timewarrior_datetime::parse(text.trim())
+            .map_err(|error| format!("invalid timestamp '{}': {}", text.trim(), error))
+    };
+    let (start, end) = match times.split_once(" - ") {
+        Some((start, end)) => (timestamp(start)?, Some(timestamp(end)?)),
+        None => (timestamp(times)?, None),
+    };
+
+    let mut tags = vec![];
+    let mut annotation: Option<Vec<String>> = None;
+    for (word, quoted) in words(rest) {
+        match &mut annotation {
+            Some(words) => words.push(word),
+            None if word == "#" && !quoted => annotation = Some(vec![]),
+            None => tags.push(word),
+        }
+    }
+    Ok(Interval {
+        start,
+        end,
+        tags,
+        annotation: annotation
+            .map(|words| words.join(" "))
+            .filter(|annotation| !annotation.is_empty()),
+    })
+}
+
+/// Parses `reports.grouped.range`: `day`, `yesterday`, `week`, `lastweek`,
+/// `month`, `lastmonth`, `all`, or `START..END` where either side is a
+/// `YYYY-MM-DD` date or a `YYYY-MM-DDTHH:MM` time and may be left out. A
+/// date as the end includes that day. Returns local start and end times.
+pub fn parse_range(
+    value: &str,
+    today: NaiveDate,
+) -> Option<(Option<NaiveDateTime>, Option<NaiveDateTime>)> {
+    let midnight = |date: NaiveDate| date.and_hms_opt(0, 0, 0);
+    let period = |period: Period, back: bool| {
+        let start = period.start_of(today);
+        let start = match back {
+            true => period.start_of(start - Duration::days(1)),
+            false => start,
+        };
+        Some((midnight(start), midnight(period.next(start))))
+    };
+    let bound = |text: &str, end: bool| -> Option<Option<NaiveDateTime>> {
+        match text.trim() {
+            "" => Some(None),
+            text => match NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M") {
+                Ok(time) => Some(Some(time)),
+                Err(_) => {
+                    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?;
+                    Some(midnight(match end {
+                        true => date + Duration::days(1),
+                        false => date,
+                    }))
+                }
+            },
+        }
+    };
+    match value.trim() {
+        "" | "day" | "today" => period(Period::Day, false),
+        "yesterday" => period(Period::Day, true),
+        "week" => period(Period::Week, false),
+        "lastweek" => period(Period::Week, true),
+        "month" => period(Period::Month, false),
+        "lastmonth" => period(Period::Month, true),
+        "all" => Some((None, None)),
+        value => {
+            let (start, end) = value.split_once("..")?;
+            Some((bound(start, false)?, bound(end, true)?))
+        }
+    }
+}
+
+/// A timewarrior database directory.
+#[derive(Debug, Clone)]
+pub struct Database {
+    pub path: PathBuf,
+}
+
+impl Database {
+    pub fn new(path: impl Into<PathBuf>) -> Database {
+        Database { path: path.into() }
+    }
+
+    /// The settings in `timewarrior.cfg`; none if there is no such file.
+    pub fn config(&self) -> Result<Vec<(String, String)>, Error> {
+        let path = self.path.join("timewarrior.cfg");
+        match path.exists() {
+            true => read_config(&path),
+            false => Ok(vec![]),
+        }
+    }
+
+    /// The intervals overlapping `range`, in the order they were recorded.
+    /// Only the monthly files that can hold such intervals are read. Lines
+    /// that cannot be parsed fail the read unless `lenient` is set, in which
+    /// case they are returned as warnings.
+    pub fn intervals(
+        &self,
+        range: &ReportRange,
+        lenient: bool,
+    ) -> Result<(Vec<Interval>, Vec<Error>), Error> {
+        let directory = self.path.join("data");
+        let mut files: Vec<(NaiveDate, PathBuf)> = std::fs::read_dir(&directory)
+            .map_err(file_error(&directory))?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let name = path.file_name()?.to_str()?.strip_suffix(".data")?;
+                let month = NaiveDate::parse_from_str(&format!("{}-01", name), "%Y-%m-%d").ok()?;
+                Some((month, path))
+            })
+            .collect();
+        files.sort();
+
+        // Intervals are filed under the month they start in, so one that
+        // started the month before the range can still reach into it.
+        let first = range.start.map(|start| {
+            let month = Period::Month.start_of(start.date_naive());
+            Period::Month.start_of(month - Duration::days(1))
+        });
+        let last = range.end.map(|end| end.date_naive());
+        let mut intervals = vec![];
+        let mut warnings = vec![];
+        for (month, path) in files {
+            if first.is_some_and(|first| month < first) || last.is_some_and(|last| month > last) {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).map_err(file_error(&path))?;
+            for (index, line) in text.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match parse_interval(line.trim()) {
+                    Ok(interval) => {
+                        if interval.clipped_bounds(range).is_some() {
+                            intervals.push(interval);
+                        }
+                    }
+                    Err(message) => {
+                        let error = Error::DataFile {
+                            path: path.clone(),
+                            line: index + 1,
+                            text: line.to_string(),
+                            message,
+                        };
+                        if !lenient {
+                            return Err(error);
+                        }
+                        warnings.push(error);
+                    }
+                }
+            }
+        }
+        intervals.sort_by_key(|interval| interval.start);
+        Ok((intervals, warnings))
+    }
+
+    /// Reads the database like timewarrior would pass it to the extension:
+    /// the settings from `timewarrior.cfg` and `overrides`, and the
+    /// intervals within `reports.grouped.range` (default: today).
+    pub fn read(&self, overrides: &[(String, String)]) -> Result<Data, Error> {
+        let mut settings: HashMap<String, Value> = HashMap::new();
+        for (key, value) in self.config()?.into_iter().chain(overrides.iter().cloned()) {
+            settings.insert(key, Value(value));
+        }
+        settings.insert(
+            String::from("temp.db"),
+            Value(self.path.display().to_string()),
+        );
+
+        let lenient = settings
+            .get("reports.grouped.lenient")
+            .is_some_and(Value::value_to_bool);
+        let range_name = format!("{}range", SETTINGS_PREFIX);
+        let range = settings
+            .get(&range_name)
+            .map_or(String::new(), |value| value.0.clone());
+        let mut data = Data::new(settings, vec![], vec![])?;
+        let (start, end) =
+            parse_range(&range, data.now.date_naive()).ok_or_else(|| Error::Setting {
+                name: range_name,
+                value: range.clone(),
+                message: String::from(
+                    "expected day, yesterday, week, lastweek, month, lastmonth, all or START..END",
+                ),
+            })?;
+        let start = start.map(|local| data.timezone.from_local(&local));
+        let end = end.map(|local| data.timezone.from_local(&local));
+        for (name, bound) in [("temp.report.start", start), ("temp.report.end", end)] {
+            let value = bound.map(|bound| timewarrior_datetime::format(&bound));
+            data.settings
+                .insert(name.to_string(), Value(value.unwrap_or_default()));
+        }
+        data.range.start = start;
+        data.range.end = end;
+
+        let (intervals, warnings) = self.intervals(&data.report_range(), lenient)?;
+        data.intervals = intervals
+            .into_iter()
+            .map(|interval| interval.with_timezone(&data.timezone))
+            .collect();
+        data.warnings = warnings;
+        Ok(data)
+    }
+
+    /// Reads the period before `data`'s report range, with `data`'s
+    /// `reports.grouped.*` settings, for [`Data::compare`].
+    /// Fails for an open report range.
+    pub fn read_previous(&self, data: &Data) -> Result<Data, Error> {
+        let settings: HashMap<String, Value> = data
+            .settings
+            .iter()
+            .filter(|(key, _)| key.starts_with(SETTINGS_PREFIX))
+            .map(|(key, value)| (key.clone(), Value(value.0.clone())))
+            .collect();
+        let lenient = settings
+            .get("reports.grouped.lenient")
+            .is_some_and(Value::value_to_bool);
+        let range = previous_range(data)?;
+        let (intervals, warnings) = self.intervals(&range, lenient)?;
+        let mut previous = Data::new(settings, intervals, warnings)?;
+        previous.range = range;
+        Ok(previous)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M").unwrap()
+    }
+
+    #[test]
+    fn parses_intervals_with_quoted_tags_and_annotation() {
+        let interval = parse_interval(
+            r#"inc 20231015T080000Z - 20231015T093000Z # api "two words" "say \"hi\"" # "fix #42""#,
+        )
+        .unwrap();
+        assert_eq!(interval.start.to_rfc3339(), "2023-10-15T08:00:00+00:00");
+        assert_eq!(
+            interval.end.map(|end| end.to_rfc3339()),
+            Some(String::from("2023-10-15T09:30:00+00:00"))
+        );
+        assert_eq!(interval.tags, ["api", "two words", "say \"hi\""]);
+        assert_eq!(interval.annotation.as_deref(), Some("fix #42"));
+    }
+
+    #[test]
+    fn parses_running_and_untagged_intervals() {
+        let interval = parse_interval("inc 20231015T080000Z").unwrap();
+        assert!(interval.end.is_none());
+        assert!(interval.tags.is_empty());
+        assert!(interval.annotation.is_none());
+
+        // A quoted `#` is a tag, not the start of the annotation.
+        let interval = parse_interval(r##"inc 20231015T080000Z # "#" # "##).unwrap();
+        assert_eq!(interval.tags, ["#"]);
+        assert!(interval.annotation.is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_interval_lines() {
+        assert!(parse_interval("exc 20231015T080000Z").is_err());
+        assert!(parse_interval("inc 2023-10-15 - 20231015T093000Z").is_err());
+    }
+
+    #[test]
+    fn reads_nested_config_blocks_and_imports() {
+        let directory =
+            std::env::temp_dir().join(format!("timewarrior-grouped-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(
+            directory.join("timewarrior.cfg"),
+            "# comment\n\
+             import theme.cfg\n\
+             define reports:\n\
+             \x20 grouped:\n\
+             \x20   sort = title # trailing comment\n\
+             \x20   tree:\n\
+             \x20     depth = 1\n\
+             \x20 day.hours = auto\n\
+             reports.grouped.min_width = 20\n",
+        )
+        .unwrap();
+        std::fs::write(directory.join("theme.cfg"), "color = off\n").unwrap();
+
+        let settings = read_config(&directory.join("timewarrior.cfg"));
+        std::fs::remove_dir_all(&directory).unwrap();
+        let settings: Vec<(&str, &str)> = settings
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            settings,
+            [
+                ("color", "off"),
+                ("reports.grouped.sort", "title"),
+                ("reports.grouped.tree.depth", "1"),
+                ("reports.day.hours", "auto"),
+                ("reports.grouped.min_width", "20"),
+            ]
+        );
+    }
+
+    #[test]
+    fn fails_for_a_missing_config() {
+        let path = Path::new("/nonexistent/timewarrior.cfg");
+        assert!(matches!(read_config(path), Err(Error::File { .. })));
+    }
+
+    #[test]
+    fn parses_named_ranges_relative_to_today() {
+        // A Wednesday.
+        let today = NaiveDate::from_ymd_opt(2023, 10, 18).unwrap();
+        let range = |value| parse_range(value, today).unwrap();
+        assert_eq!(
+            range("day"),
+            (
+                Some(time("2023-10-18T00:00")),
+                Some(time("2023-10-19T00:00"))
+            )
+        );
+        assert_eq!(
+            range("yesterday"),
+            (
+                Some(time("2023-10-17T00:00")),
+                Some(time("2023-10-18T00:00"))
+            )
+        );
+        assert_eq!(
+            range("week"),
+            (
+                Some(time("2023-10-16T00:00")),
+                Some(time("2023-10-23T00:00"))
+            )
+        );
+        assert_eq!(
+            range("lastweek"),
+            (
+                Some(time("2023-10-09T00:00")),
+                Some(time("2023-10-16T00:00"))
+            )
+        );
+        assert_eq!(
+            range("lastmonth"),
+            (
+                Some(time("2023-09-01T00:00")),
+                Some(time("2023-10-01T00:00"))
+            )
+        );
+        assert_eq!(range("all"), (None, None));
+    }
+
+    #[test]
+    fn parses_explicit_ranges() {
+        let today = NaiveDate::from_ymd_opt(2023, 10, 18).unwrap();
+        let range = |value| parse_range(value, today);
+        // An end date includes that day.
+        assert_eq!(
+            range("2023-10-01..2023-10-07"),
+            Some((
+                Some(time("2023-10-01T00:00")),
+                Some(time("2023-10-08T00:00"))
+            ))
+        );
+        assert_eq!(
+            range("2023-10-01T08:30..2023-10-01T17:00"),
+            Some((
+                Some(time("2023-10-01T08:30")),
+                Some(time("2023-10-01T17:00"))
+            ))
+        );
+        assert_eq!(
+            range("2023-10-01.."),
+            Some((Some(time("2023-10-01T00:00")), None))
+        );
+        assert_eq!(range("fortnight"), None);
+        assert_eq!(range("2023-10-01..tomorrow"), None);
+    }
+}
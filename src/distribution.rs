@@ -0,0 +1,142 @@
+//! When the time was tracked: per hour of the day and per day of the week.
+
+use crate::group::{interval_groups, Report};
+use crate::input::{Data, Interval};
+use crate::options::Options;
+use chrono::{Datelike, Duration, Timelike};
+
+/// Settings for the distribution section, from
+/// `reports.grouped.distribution.*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DistributionConfig {
+    pub hours: bool,
+    pub weekdays: bool,
+    /// Whether every top-level group gets its own histograms as well.
+    pub groups: bool,
+}
+
+/// The names of [`Distribution::weekdays`]' entries.
+pub const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Time per local hour of the day and per weekday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Distribution {
+    /// Indexed by hour, 0 to 23.
+    pub hours: [Duration; 24],
+    /// Indexed by days from Monday.
+    pub weekdays: [Duration; 7],
+}
+
+impl Default for Distribution {
+    fn default() -> Self {
+        Distribution {
+            hours: [Duration::zero(); 24],
+            weekdays: [Duration::zero(); 7],
+        }
+    }
+}
+
+impl Distribution {
+    pub fn total(&self) -> Duration {
+        self.weekdays
+            .iter()
+            .fold(Duration::zero(), |sum, duration| sum + *duration)
+    }
+}
+
+impl Data {
+    /// Splits the clipped duration of every interval `filter` accepts at the
+    /// local hour boundaries of the report's timezone and sums the pieces
+    /// by hour and weekday. Hours repeated by a DST change count twice
+    /// towards their hour.
+    pub fn distribution(&self, filter: impl Fn(&Interval) -> bool) -> Distribution {
+        let range = self.report_range();
+        let mut distribution = Distribution::default();
+        for interval in self.intervals.iter().filter(|interval| filter(interval)) {
+            let Some((mut start, end)) = interval.clipped_bounds(&range) else {
+                continue;
+            };
+            while start < end {
+                let local = self.timezone.convert(&start);
+                let hour = local
+                    .naive_local()
+                    .date()
+                    .and_hms_opt(local.hour(), 0, 0)
+                    .unwrap();
+                let next = self.timezone.from_local(&(hour + Duration::hours(1)));
+                let piece_end = next.max(start + Duration::seconds(1)).min(end);
+                let piece = piece_end - start;
+                let hour = local.hour() as usize;
+                let weekday = local.weekday().num_days_from_monday() as usize;
+                distribution.hours[hour] = distribution.hours[hour] + piece;
+                distribution.weekdays[weekday] = distribution.weekdays[weekday] + piece;
+                start = piece_end;
+            }
+        }
+        distribution
+    }
+
+    /// The distribution of every top-level line of `report`, built from this
+    /// data with `options`, titled like the line.
+    pub fn group_distributions(
+        &self,
+        report: &Report,
+        options: &Options,
+    ) -> Vec<(String, Distribution)> {
+        report
+            .lines
+            .iter()
+            .filter(|line| line.depth == 0)
+            .map(|line| {
+                let distribution = self.distribution(|interval| {
+                    interval_groups(interval, options).contains(&line.row.key())
+                });
+                (line.row.title.clone(), distribution)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::test_data;
+
+    fn minutes(durations: &[Duration]) -> Vec<(usize, i64)> {
+        durations
+            .iter()
+            .enumerate()
+            .filter(|(_, duration)| !duration.is_zero())
+            .map(|(index, duration)| (index, duration.num_minutes()))
+            .collect()
+    }
+
+    #[test]
+    fn splits_time_at_local_hours() {
+        let data = test_data(
+            &[("timezone", "Europe/Berlin")],
+            &["0830 - 1015 # code", "1400 - 1430 # docs"],
+        );
+        let distribution = data.distribution(|_| true);
+        assert_eq!(
+            minutes(&distribution.hours),
+            [(10, 30), (11, 60), (12, 15), (16, 30)]
+        );
+        assert_eq!(minutes(&distribution.weekdays), [(6, 135)]);
+        assert_eq!(distribution.total(), Duration::minutes(135));
+
+        let options = Options::from_data(&data).unwrap();
+        let groups: Vec<(String, Vec<(usize, i64)>)> = data
+            .group_distributions(&data.report(&options), &options)
+            .into_iter()
+            .map(|(title, distribution)| (title, minutes(&distribution.hours)))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                ("code".into(), vec![(10, 30), (11, 60), (12, 15)]),
+                ("docs".into(), vec![(16, 30)]),
+            ]
+        );
+    }
+}
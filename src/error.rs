@@ -0,0 +1,116 @@
+//! The error type shared by the reader, the options and the renderers.
+
+use std::fmt;
+
+/// Everything that can go wrong between reading timewarrior's output and
+/// printing a report.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the input or writing the report failed.
+    Io(std::io::Error),
+    /// Reading or writing a file other than the input failed.
+    File {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    /// A line of a timewarrior data file could not be read. `line` is
+    /// 1-based.
+    DataFile {
+        path: std::path::PathBuf,
+        line: usize,
+        text: String,
+        message: String,
+    },
+    /// A command-line argument was not understood.
+    Argument(String),
+    /// A header line was not a `name: value` pair. `line` is 1-based.
+    Header { line: usize, text: String },
+    /// The interval data was not valid JSON, or an interval in it could not
+    /// be decoded. `line` and `column` are 1-based positions in the whole
+    /// input and `text` is the offending input line.
+    Json {
+        line: usize,
+        column: usize,
+        text: String,
+        source: serde_json::Error,
+    },
+    /// A timestamp setting such as `temp.report.start` could not be parsed.
+    Timestamp {
+        name: String,
+        text: String,
+        source: chrono::ParseError,
+    },
+    /// A setting had a value that is not allowed for it.
+    Setting {
+        name: String,
+        value: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "I/O error: {}", error),
+            Error::File { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::DataFile {
+                path,
+                line,
+                text,
+                message,
+            } => write!(
+                f,
+                "{}, line {}: {}\n    {}",
+                path.display(),
+                line,
+                message,
+                text
+            ),
+            Error::Argument(message) => write!(f, "{}", message),
+            Error::Header { line, text } => write!(
+                f,
+                "line {}: expected a 'name: value' header line, got '{}'",
+                line, text
+            ),
+            Error::Json {
+                line,
+                column,
+                text,
+                source,
+            } => write!(
+                f,
+                "line {}, column {}: {}\n    {}",
+                line,
+                column,
+                source.to_string().split(" at line ").next().unwrap_or(""),
+                text
+            ),
+            Error::Timestamp { name, text, source } => {
+                write!(f, "{}: invalid timestamp '{}': {}", name, text, source)
+            }
+            Error::Setting {
+                name,
+                value,
+                message,
+            } => write!(f, "{}: invalid value '{}': {}", name, value, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            Error::File { source, .. } => Some(source),
+            Error::Json { source, .. } => Some(source),
+            Error::Timestamp { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
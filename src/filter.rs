@@ -0,0 +1,125 @@
+//! Tag rules applied to the intervals before they are grouped: aliases,
+//! include and exclude filters, and tags stripped from titles.
+
+use crate::input::Data;
+use crate::pattern::TagFilter;
+use std::collections::HashMap;
+
+/// The tag rules from `reports.grouped.{alias.*,include,exclude,strip}`.
+#[derive(Debug, Clone, Default)]
+pub struct TagRules {
+    /// Tags renamed before anything else happens, e.g. `js` to
+    /// `javascript`.
+    pub aliases: HashMap<String, String>,
+    /// When not empty, only intervals with a matching tag are reported.
+    pub include: Vec<TagFilter>,
+    /// Intervals with a matching tag are dropped.
+    pub exclude: Vec<TagFilter>,
+    /// Tags removed from the intervals, which keep their time.
+    pub strip: Vec<TagFilter>,
+}
+
+impl TagRules {
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+            && self.include.is_empty()
+            && self.exclude.is_empty()
+            && self.strip.is_empty()
+    }
+
+    /// `tags` with aliases applied and duplicates removed, or `None` when
+    /// the filters drop the interval.
+    fn apply(&self, tags: &[String]) -> Option<Vec<String>> {
+        let mut renamed: Vec<String> = vec![];
+        for tag in tags {
+            let tag = self.aliases.get(tag).unwrap_or(tag);
+            if !renamed.contains(tag) {
+                renamed.push(tag.clone());
+            }
+        }
+        let matches = |filters: &[TagFilter]| {
+            renamed
+                .iter()
+                .any(|tag| filters.iter().any(|filter| filter.matches(tag)))
+        };
+        if (!self.include.is_empty() && !matches(&self.include)) || matches(&self.exclude) {
+            return None;
+        }
+        renamed.retain(|tag| !self.strip.iter().any(|filter| filter.matches(tag)));
+        Some(renamed)
+    }
+}
+
+impl Data {
+    /// Applies `rules` to every interval: renames aliased tags, drops the
+    /// intervals the include and exclude filters reject into
+    /// [`Data::filtered`] and removes stripped tags. Filters see the renamed
+    /// tags, before stripping.
+    ///
+    /// Reports and renderers group `self.intervals` as they are, so this has
+    /// to be called once, before rendering, for `options.tags` to apply.
+    /// Calling it twice would follow alias chains a second time.
+    pub fn apply_tag_rules(&mut self, rules: &TagRules) {
+        if rules.is_empty() {
+            return;
+        }
+        for mut interval in std::mem::take(&mut self.intervals) {
+            match rules.apply(&interval.tags) {
+                Some(tags) => {
+                    interval.tags = tags;
+                    self.intervals.push(interval);
+                }
+                None => self.filtered.push(interval),
+            }
+        }
+        if let Some(previous) = self.previous.as_deref_mut() {
+            previous.apply_tag_rules(rules);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::test_data;
+    use crate::options::Options;
+
+    #[test]
+    fn renames_filters_and_strips_tags() {
+        let mut data = test_data(
+            &[
+                ("alias.js", "javascript"),
+                ("include", "client:*,javascript"),
+                ("exclude", "/^meet/"),
+                ("strip", "billable"),
+            ],
+            &[
+                "0800 - 0900 # client:acme js billable",
+                "0900 - 0930 # js javascript",
+                "0930 - 1000 # client:acme meeting",
+                "1000 - 1030 # docs",
+                "1030 - 1100 # billable javascript",
+            ],
+        );
+        let options = Options::from_data(&data).unwrap();
+        data.apply_tag_rules(&options.tags);
+        let tags: Vec<Vec<String>> = data
+            .intervals
+            .iter()
+            .map(|interval| interval.tags.clone())
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                vec!["client:acme", "javascript"],
+                vec!["javascript"],
+                vec!["javascript"],
+            ]
+        );
+        let filtered: Vec<String> = data
+            .filtered
+            .iter()
+            .map(|interval| interval.title())
+            .collect();
+        assert_eq!(filtered, ["client:acme, meeting", "docs"]);
+    }
+}
@@ -0,0 +1,186 @@
+//! Hour targets per tag group, scaled to the report range.
+
+use crate::group::{GroupKey, Report};
+use crate::input::Data;
+use crate::pivot::Period;
+use chrono::{DateTime, Duration, FixedOffset};
+
+/// A target for the report line titled `title`, e.g. 20 hours per week.
+#[derive(Debug, Clone)]
+pub struct Goal {
+    pub title: String,
+    /// The group `title` stands for.
+    pub group: GroupKey,
+    pub target: Duration,
+    /// The period `target` is for; `None` means the whole report range.
+    pub period: Option<Period>,
+}
+
+impl Goal {
+    /// Parses `20h/week`, `4h/day`, `80h/month` or, for the whole report
+    /// range, just a duration such as `20h`.
+    pub fn parse(title: &str, value: &str) -> Option<Goal> {
+        let (duration, period) = match value.split_once('/') {
+            Some((duration, period)) => (duration, Some(period.trim())),
+            None => (value, None),
+        };
+        let period = match period {
+            None => None,
+            Some("day") => Some(Period::Day),
+            Some("week") => Some(Period::Week),
+            Some("month") => Some(Period::Month),
+            Some(_) => return None,
+        };
+        Some(Goal {
+            title: title.to_string(),
+            group: GroupKey::parse(title),
+            target: crate::options::parse_duration(duration)?,
+            period,
+        })
+    }
+
+    /// The target for `start` to `end`: the per-period target times the
+    /// number of periods covered, counting partly covered periods by the
+    /// share of them inside the range.
+    pub fn scaled_target(
+        &self,
+        data: &Data,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> Duration {
+        let Some(period) = self.period else {
+            return self.target;
+        };
+        let mut periods = 0.0;
+        let mut date = period.start_of(start.date_naive());
+        loop {
+            let period_start = data
+                .timezone
+                .from_local(&date.and_hms_opt(0, 0, 0).unwrap());
+            if period_start >= end {
+                break;
+            }
+            date = period.next(date);
+            let period_end = data
+                .timezone
+                .from_local(&date.and_hms_opt(0, 0, 0).unwrap());
+            let covered = period_end.min(end) - period_start.max(start);
+            if covered > Duration::zero() {
+                periods +=
+                    covered.num_seconds() as f64 / (period_end - period_start).num_seconds() as f64;
+            }
+        }
+        Duration::seconds((self.target.num_seconds() as f64 * periods).round() as i64)
+    }
+}
+
+/// How far a report line got towards its goal.
+#[derive(Debug, Clone)]
+pub struct GoalProgress {
+    pub title: String,
+    /// The goal's target scaled to the report range.
+    pub target: Duration,
+    pub actual: Duration,
+}
+
+impl GoalProgress {
+    /// The time still missing; negative once the target is exceeded.
+    pub fn remaining(&self) -> Duration {
+        self.target - self.actual
+    }
+
+    /// The actual time as a share of the target, 1.0 when it is met.
+    pub fn fraction(&self) -> f64 {
+        match self.target.num_seconds() {
+            0 => 1.0,
+            target => self.actual.num_seconds() as f64 / target as f64,
+        }
+    }
+
+    pub fn is_met(&self) -> bool {
+        self.actual >= self.target
+    }
+}
+
+impl Data {
+    /// The progress of every goal against the line of `report` for the
+    /// goal's group, in the order of `goals`. Lines without time count as
+    /// zero. Without a report range, the range spans the intervals.
+    pub fn goal_progress(&self, goals: &[Goal], report: &Report) -> Vec<GoalProgress> {
+        let range = self.report_range();
+        let bounds: Vec<_> = self
+            .intervals
+            .iter()
+            .filter_map(|interval| interval.clipped_bounds(&range))
+            .collect();
+        let start = range
+            .start
+            .or_else(|| bounds.iter().map(|(start, _)| *start).min());
+        let end = range
+            .end
+            .or_else(|| bounds.iter().map(|(_, end)| *end).max());
+        goals
+            .iter()
+            .map(|goal| GoalProgress {
+                title: goal.title.clone(),
+                target: match (start, end) {
+                    (Some(start), Some(end)) => goal.scaled_target(self, start, end),
+                    _ => match goal.period {
+                        None => goal.target,
+                        Some(_) => Duration::zero(),
+                    },
+                },
+                actual: report
+                    .lines
+                    .iter()
+                    .find(|line| line.row.key() == goal.group)
+                    .map_or(Duration::zero(), |line| line.row.duration),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::test_data;
+    use crate::options::Options;
+
+    #[test]
+    fn parses_targets_per_period() {
+        let goal = Goal::parse("client:acme", "20h/week").unwrap();
+        assert_eq!(goal.target, Duration::hours(20));
+        assert_eq!(goal.period, Some(Period::Week));
+        assert_eq!(Goal::parse("x", "90min").unwrap().period, None);
+        assert!(Goal::parse("x", "2h/fortnight").is_none());
+        assert!(Goal::parse("x", "lots").is_none());
+    }
+
+    #[test]
+    fn measures_the_line_of_the_goal_group() {
+        let data = test_data(
+            &[
+                ("annotation", "text"),
+                ("goal.42", "4h/week"),
+                ("goal./ 42", "2h"),
+                ("goal.docs", "1h/day"),
+            ],
+            &["0800 - 0900 # # 42", "0900 - 0930 # 42"],
+        );
+        let options = Options::from_data(&data).unwrap();
+        let report = data.report(&options);
+        let progress = data.goal_progress(&options.goals, &report);
+        let progress: Vec<(&str, i64, i64)> = progress
+            .iter()
+            .map(|progress| {
+                let minutes = (progress.target.num_minutes(), progress.actual.num_minutes());
+                (progress.title.as_str(), minutes.0, minutes.1)
+            })
+            .collect();
+        // A one-day range gets a seventh of the weekly target.
+        assert_eq!(
+            progress,
+            [("/ 42", 120, 60), ("42", 34, 30), ("docs", 60, 0)]
+        );
+    }
+}
@@ -0,0 +1,650 @@
+//! Grouping intervals into report rows.
+
+use crate::input::{Data, Interval};
+use crate::options::{Grouping, Options, SortKey};
+use crate::render::pad_string;
+use std::cmp::Ordering;
+
+/// The total time of all intervals that share a title.
+#[derive(Debug, Clone)]
+pub struct GroupReportRow {
+    pub title: String,
+    /// The tags the title was built from; a single tree path for tree
+    /// grouping.
+    pub tags: Vec<String>,
+    /// The annotation key the row groups as well as its tags, with
+    /// `reports.grouped.annotation`.
+    pub annotation: Option<String>,
+    pub duration: chrono::Duration,
+}
+
+impl GroupReportRow {
+    /// The group the row sums, as [`interval_groups`] returns it.
+    pub fn key(&self) -> GroupKey {
+        GroupKey {
+            tags: self.tags.clone(),
+            annotation: self.annotation.clone(),
+        }
+    }
+
+    /// The title right-aligned to `len` columns.
+    pub fn padded_title(&self, len: usize) -> String {
+        pad_string(&self.title, len)
+    }
+}
+
+impl Data {
+    /// Groups the intervals as in `options.grouping` for the flat
+    /// groupings, summing each interval's duration clipped to the report
+    /// range under every group [`interval_groups`] returns for it. Rows keep
+    /// the order in which their group first appears.
+    pub fn grouped_report_rows(&self, options: &Options) -> Vec<GroupReportRow> {
+        self.grouped_report_rows_by(|interval| interval_groups(interval, options))
+    }
+
+    /// Groups the intervals under each group `groups` returns for them,
+    /// counting an interval at most once per group. Row titles are built by
+    /// [`GroupKey::title`]. Intervals with no time in the report range, such
+    /// as an excluded running one, are left out.
+    pub fn grouped_report_rows_by(
+        &self,
+        groups: impl Fn(&Interval) -> Vec<GroupKey>,
+    ) -> Vec<GroupReportRow> {
+        let range = self.report_range();
+        let mut rows: Vec<GroupReportRow> = vec![];
+        self.intervals.iter().for_each(|interval| {
+            if interval.clipped_bounds(&range).is_none() {
+                return;
+            }
+            let duration = interval.clipped_duration(&range);
+            let groups = groups(interval);
+            groups.iter().enumerate().for_each(|(index, group)| {
+                if groups[..index].contains(group) {
+                    return;
+                }
+                let row = rows.iter_mut().find(|row| row.key() == *group);
+                match row {
+                    Some(row) => {
+                        row.duration = row.duration.checked_add(&duration).unwrap();
+                    }
+                    None => rows.push(GroupReportRow {
+                        title: group.title(),
+                        tags: group.tags.clone(),
+                        annotation: group.annotation.clone(),
+                        duration,
+                    }),
+                };
+            });
+        });
+        rows
+    }
+}
+
+impl Data {
+    /// The sum of all intervals clipped to the report range.
+    pub fn total_duration(&self) -> chrono::Duration {
+        let range = self.report_range();
+        self.intervals
+            .iter()
+            .fold(chrono::Duration::zero(), |total, interval| {
+                total + interval.clipped_duration(&range)
+            })
+    }
+
+    /// Groups the intervals into a tree of the nodes [`interval_groups`]
+    /// returns for them, so `client:acme:api` counts towards `client`,
+    /// `client:acme` and `client:acme:api`. An interval counts once towards
+    /// each of its nodes, so siblings overlap when an interval carries
+    /// several tags. With `options.annotation`, the annotation key is a
+    /// child of every full path.
+    pub fn grouped_report_tree(&self, options: &Options) -> Vec<TreeNode> {
+        self.grouped_report_tree_by(&options.tree_separator, |interval| {
+            interval_groups(interval, options)
+        })
+    }
+
+    /// Like [`Data::grouped_report_tree`], but places each interval in the
+    /// nodes `groups` returns for it: the path of a group's single tag split
+    /// on `separator`, followed by its annotation key.
+    pub fn grouped_report_tree_by(
+        &self,
+        separator: &str,
+        groups: impl Fn(&Interval) -> Vec<GroupKey>,
+    ) -> Vec<TreeNode> {
+        let range = self.report_range();
+        let mut roots: Vec<TreeNode> = vec![];
+        self.intervals.iter().for_each(|interval| {
+            if interval.clipped_bounds(&range).is_none() {
+                return;
+            }
+            let duration = interval.clipped_duration(&range);
+            let groups = groups(interval);
+            groups.iter().enumerate().for_each(|(index, group)| {
+                if !groups[..index].contains(group) {
+                    let node = tree_node(&mut roots, group, separator);
+                    node.row.duration = node.row.duration + duration;
+                }
+            });
+        });
+        roots
+    }
+}
+
+/// The node of `group` in `nodes`, created along with any missing
+/// ancestors.
+fn tree_node<'a>(
+    nodes: &'a mut Vec<TreeNode>,
+    group: &GroupKey,
+    separator: &str,
+) -> &'a mut TreeNode {
+    let path = group.tags.concat();
+    let segments: Vec<&str> = path.split(separator).collect();
+    let mut nodes = nodes;
+    let mut index = 0;
+    for depth in 0..segments.len() {
+        if depth > 0 {
+            nodes = &mut nodes[index].children;
+        }
+        let node = tree_group(segments[..=depth].join(separator), None);
+        index = child(nodes, segments[depth], node);
+    }
+    if let Some(key) = &group.annotation {
+        nodes = &mut nodes[index].children;
+        index = child(nodes, key, group.clone());
+    }
+    &mut nodes[index]
+}
+
+/// The group of the tree node at `path`. The untagged root has no tags,
+/// like untagged rows in the flat groupings.
+fn tree_group(path: String, annotation: Option<String>) -> GroupKey {
+    GroupKey {
+        tags: match path.is_empty() {
+            true => vec![],
+            false => vec![path],
+        },
+        annotation,
+    }
+}
+
+/// The index of the child named `name` that sums `group`, added with no
+/// time if there is none yet.
+fn child(nodes: &mut Vec<TreeNode>, name: &str, group: GroupKey) -> usize {
+    match nodes.iter().position(|node| node.row.key() == group) {
+        Some(index) => index,
+        None => {
+            nodes.push(TreeNode {
+                name: name.to_string(),
+                row: GroupReportRow {
+                    title: group.title(),
+                    tags: group.tags,
+                    annotation: group.annotation,
+                    duration: chrono::Duration::zero(),
+                },
+                children: vec![],
+            });
+            nodes.len() - 1
+        }
+    }
+}
+
+/// A node of the tag tree built by [`Data::grouped_report_tree`]. The row's
+/// title is the full path, e.g. `client:acme`, and its duration includes all
+/// descendants.
+#[derive(Debug)]
+pub struct TreeNode {
+    /// The last path segment, e.g. `acme`.
+    pub name: String,
+    pub row: GroupReportRow,
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    /// Sorts the node's children, recursively.
+    pub fn sort(&mut self, sort: SortKey, reverse: bool) {
+        sort_tree(&mut self.children, sort, reverse);
+    }
+
+    /// The node followed by its descendants, depth-first, each with its
+    /// depth below the node. Nodes deeper than `max_depth` are left out;
+    /// their time is still included in their ancestors' subtotals.
+    pub fn flatten(&self, max_depth: Option<usize>) -> Vec<(usize, &TreeNode)> {
+        let mut nodes = vec![(0, self)];
+        if max_depth != Some(0) {
+            self.children.iter().for_each(|child| {
+                child
+                    .flatten(max_depth.map(|depth| depth - 1))
+                    .into_iter()
+                    .for_each(|(depth, node)| nodes.push((depth + 1, node)));
+            });
+        }
+        nodes
+    }
+}
+
+/// Adds the nodes of `other` missing from `nodes`, with no time, so every
+/// group of the previous period has a line to compare with.
+fn add_empty_nodes(nodes: &mut Vec<TreeNode>, other: Vec<TreeNode>) {
+    for mut node in other {
+        match nodes
+            .iter_mut()
+            .find(|current| current.row.key() == node.row.key())
+        {
+            Some(current) => add_empty_nodes(&mut current.children, node.children),
+            None => {
+                node.row.duration = chrono::Duration::zero();
+                let children = std::mem::take(&mut node.children);
+                add_empty_nodes(&mut node.children, children);
+                nodes.push(node);
+            }
+        }
+    }
+}
+
+fn compare_rows(a: &GroupReportRow, b: &GroupReportRow, sort: SortKey) -> Ordering {
+    match sort {
+        SortKey::Duration => b.duration.cmp(&a.duration),
+        SortKey::Title => a.title.cmp(&b.title),
+    }
+}
+
+/// Orders rows by `sort`, longest or alphabetically first unless `reverse`.
+pub fn sort_rows(rows: &mut [GroupReportRow], sort: SortKey, reverse: bool) {
+    rows.sort_by(|a, b| compare_rows(a, b, sort));
+    if reverse {
+        rows.reverse();
+    }
+}
+
+/// Orders tree nodes like [`sort_rows`] at every level.
+pub fn sort_tree(nodes: &mut [TreeNode], sort: SortKey, reverse: bool) {
+    nodes.sort_by(|a, b| compare_rows(&a.row, &b.row, sort));
+    if reverse {
+        nodes.reverse();
+    }
+    nodes.iter_mut().for_each(|node| node.sort(sort, reverse));
+}
+
+/// The tags `interval` is grouped by: the name of the first category rule
+/// in `options.categories` that matches it, or else its own tags.
+pub fn grouping_tags(interval: &Interval, options: &Options) -> Vec<String> {
+    match interval.category(&options.categories) {
+        Some(rule) => vec![rule.name.clone()],
+        None => interval.tags.clone(),
+    }
+}
+
+/// The key of `interval`'s annotation in `options.annotation`, if any.
+pub fn annotation_key(interval: &Interval, options: &Options) -> Option<String> {
+    interval.annotation_key(options.annotation.as_ref()?)
+}
+
+/// A group intervals are summed under: tags and, with
+/// `reports.grouped.annotation`, an annotation key. The key is kept apart
+/// from the tags so tag patterns never match it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupKey {
+    pub tags: Vec<String>,
+    pub annotation: Option<String>,
+}
+
+impl GroupKey {
+    /// The tags joined like [`Interval::title`], followed by ` / ` and the
+    /// annotation key, e.g. `PROJ-12, js / 42`. A key without tags is
+    /// titled `/ 42`, so it never reads like a tag.
+    pub fn title(&self) -> String {
+        let tags = self.tags.join(", ");
+        match &self.annotation {
+            Some(key) if tags.is_empty() => format!("/ {}", key),
+            Some(key) => format!("{} / {}", tags, key),
+            None => tags,
+        }
+    }
+
+    /// The group `title` stands for, the reverse of [`GroupKey::title`].
+    pub fn parse(title: &str) -> GroupKey {
+        let (tags, annotation) = match title.strip_prefix("/ ") {
+            Some(key) => ("", Some(key)),
+            None => match title.rsplit_once(" / ") {
+                Some((tags, key)) => (tags, Some(key)),
+                None => (title, None),
+            },
+        };
+        GroupKey {
+            tags: match tags {
+                "" => vec![],
+                tags => tags.split(", ").map(String::from).collect(),
+            },
+            annotation: annotation.map(String::from),
+        }
+    }
+}
+
+/// The groups `interval` counts under in `options.grouping`. For tree
+/// grouping these are the tree nodes the interval counts towards, down to
+/// `options.tree_depth`.
+pub fn interval_groups(interval: &Interval, options: &Options) -> Vec<GroupKey> {
+    let tags = grouping_tags(interval, options);
+    let annotation = annotation_key(interval, options);
+    let group = |tags: Vec<String>| GroupKey {
+        tags,
+        annotation: annotation.clone(),
+    };
+    match options.grouping {
+        Grouping::Combination => vec![group(tags)],
+        Grouping::Unordered => {
+            let mut tags = tags;
+            tags.sort_unstable();
+            tags.dedup();
+            vec![group(tags)]
+        }
+        Grouping::Annotation => vec![group(vec![])],
+        Grouping::Tag if tags.is_empty() => vec![group(vec![])],
+        Grouping::Tag => tags.into_iter().map(|tag| group(vec![tag])).collect(),
+        Grouping::Tree => {
+            let separator = options.tree_separator.as_str();
+            let tags = match tags.is_empty() {
+                true => vec![String::new()],
+                false => tags,
+            };
+            let mut groups: Vec<GroupKey> = vec![];
+            let mut keyed: Vec<GroupKey> = vec![];
+            tags.iter().for_each(|tag| {
+                let segments: Vec<&str> = tag.split(separator).collect();
+                let depth = options
+                    .tree_depth
+                    .map_or(segments.len(), |depth| segments.len().min(depth + 1));
+                for length in 1..=depth {
+                    let node = tree_group(segments[..length].join(separator), None);
+                    if !groups.contains(&node) {
+                        groups.push(node);
+                    }
+                }
+                // The key's node sits one level below the full path.
+                let shown = options
+                    .tree_depth
+                    .is_none_or(|depth| segments.len() <= depth);
+                let node = tree_group(tag.clone(), annotation.clone());
+                if annotation.is_some() && shown && !keyed.contains(&node) {
+                    keyed.push(node);
+                }
+            });
+            groups.extend(keyed);
+            groups
+        }
+    }
+}
+
+/// The most specific groups `interval` counts under: each full tag path,
+/// with the annotation key, for tree grouping, and the groups of
+/// [`interval_groups`] otherwise. Every other group it counts under is an
+/// ancestor of one of these.
+pub fn leaf_groups(interval: &Interval, options: &Options) -> Vec<GroupKey> {
+    if options.grouping != Grouping::Tree {
+        return interval_groups(interval, options);
+    }
+    let annotation = annotation_key(interval, options);
+    let mut tags = grouping_tags(interval, options);
+    if tags.is_empty() {
+        tags.push(String::new());
+    }
+    let mut groups: Vec<GroupKey> = vec![];
+    tags.into_iter().for_each(|tag| {
+        let group = tree_group(tag, annotation.clone());
+        if !groups.contains(&group) {
+            groups.push(group);
+        }
+    });
+    groups
+}
+
+/// Whether an interval in the leaf group `leaf` counts towards `group`:
+/// the same group or, for tree grouping, a node below it.
+pub fn group_contains(group: &GroupKey, leaf: &GroupKey, options: &Options) -> bool {
+    if options.grouping != Grouping::Tree || group.annotation.is_some() {
+        return group == leaf;
+    }
+    let (path, leaf_path) = (group.tags.concat(), leaf.tags.concat());
+    path == leaf_path || leaf_path.starts_with(&format!("{}{}", path, options.tree_separator))
+}
+
+/// One line of a report table.
+#[derive(Debug, Clone)]
+pub struct ReportLine {
+    /// The depth in the tag tree; always 0 for flat groupings.
+    pub depth: usize,
+    /// What the first column shows: the row's title, or the last path
+    /// segment for tree grouping.
+    pub label: String,
+    pub row: GroupReportRow,
+    /// Whether the running interval counts towards the line.
+    pub running: bool,
+}
+
+/// The grouped, sorted table all renderers share.
+#[derive(Debug)]
+pub struct Report {
+    pub lines: Vec<ReportLine>,
+    /// The time of all intervals, counted once each.
+    pub total: chrono::Duration,
+    /// Whether intervals count under several lines, so the lines add up to
+    /// more than the total.
+    pub overlapping: bool,
+}
+
+impl Report {
+    /// A line's share of the total, in percent.
+    pub fn percent(&self, duration: &chrono::Duration) -> f64 {
+        match self.total.num_seconds() {
+            0 => 0.0,
+            total => duration.num_seconds() as f64 / total as f64 * 100.0,
+        }
+    }
+}
+
+impl Data {
+    /// Groups the intervals as configured in `options` and sorts the rows.
+    /// Intervals matching a category rule are grouped under the category.
+    /// Tree grouping yields the tree depth-first, down to
+    /// `options.tree_depth`. With a [`Data::previous`] period, its groups
+    /// missing from this one get a line without time.
+    pub fn report(&self, options: &Options) -> Report {
+        let mut lines: Vec<ReportLine> = match options.grouping {
+            Grouping::Combination | Grouping::Unordered | Grouping::Tag | Grouping::Annotation => {
+                let mut rows = self.grouped_report_rows(options);
+                if let Some(previous) = self.previous.as_deref() {
+                    for row in previous.grouped_report_rows(options) {
+                        if !rows.iter().any(|current| current.key() == row.key()) {
+                            rows.push(GroupReportRow {
+                                duration: chrono::Duration::zero(),
+                                ..row
+                            });
+                        }
+                    }
+                }
+                sort_rows(&mut rows, options.sort, options.reverse);
+                rows.into_iter()
+                    .map(|row| ReportLine {
+                        depth: 0,
+                        label: row.title.clone(),
+                        row,
+                        running: false,
+                    })
+                    .collect()
+            }
+            Grouping::Tree => {
+                let mut tree = self.grouped_report_tree(options);
+                if let Some(previous) = self.previous.as_deref() {
+                    add_empty_nodes(&mut tree, previous.grouped_report_tree(options));
+                }
+                sort_tree(&mut tree, options.sort, options.reverse);
+                tree.iter()
+                    .flat_map(|node| node.flatten(options.tree_depth))
+                    .map(|(depth, node)| ReportLine {
+                        depth,
+                        label: node.name.clone(),
+                        row: node.row.clone(),
+                        running: false,
+                    })
+                    .collect()
+            }
+        };
+        if let Some(interval) = self.running_interval() {
+            let groups = interval_groups(interval, options);
+            lines
+                .iter_mut()
+                .for_each(|line| line.running = groups.contains(&line.row.key()));
+        }
+        let total = self.total_duration();
+        let overlapping = lines
+            .iter()
+            .filter(|line| line.depth == 0)
+            .fold(chrono::Duration::zero(), |sum, line| {
+                sum + line.row.duration
+            })
+            > total;
+        Report {
+            lines,
+            total,
+            overlapping,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::test_data;
+
+    /// The report's lines as `(depth, title, minutes)`.
+    fn lines(report: &Report) -> Vec<(usize, &str, i64)> {
+        report
+            .lines
+            .iter()
+            .map(|line| {
+                (
+                    line.depth,
+                    line.row.title.as_str(),
+                    line.row.duration.num_minutes(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_by_the_first_matching_category() {
+        let data = test_data(
+            &[
+                ("grouping", "tag"),
+                ("category.2", "docs -> writing"),
+                ("category.1", "client:* -> clients"),
+            ],
+            &[
+                "0800 - 0900 # client:acme docs",
+                "0900 - 0930 # client:beta",
+                "0930 - 1000 # docs",
+                "1000 - 1015 # misc",
+            ],
+        );
+        let options = Options::from_data(&data).unwrap();
+        assert_eq!(
+            lines(&data.report(&options)),
+            [(0, "clients", 90), (0, "writing", 30), (0, "misc", 15)]
+        );
+    }
+
+    #[test]
+    fn builds_the_tree_from_the_interval_groups() {
+        let data = test_data(
+            &[("grouping", "tree"), ("sort", "title"), ("tree.depth", "1")],
+            &[
+                "0800 - 0900 # client:acme:api code",
+                "0900 - 0930 # client:beta",
+                "1100 # client:acme:web",
+            ],
+        );
+        let options = Options::from_data(&data).unwrap();
+        let report = data.report(&options);
+        assert_eq!(
+            lines(&report),
+            [
+                (0, "client", 150),
+                (1, "client:acme", 120),
+                (1, "client:beta", 30),
+                (0, "code", 60),
+            ]
+        );
+        // Every line the running interval counts towards is marked, and no
+        // other.
+        let running: Vec<&str> = report
+            .lines
+            .iter()
+            .filter(|line| line.running)
+            .map(|line| line.row.title.as_str())
+            .collect();
+        assert_eq!(running, ["client", "client:acme"]);
+        assert!(report.overlapping);
+    }
+
+    #[test]
+    fn keeps_annotation_keys_apart_from_tags() {
+        let data = test_data(
+            &[("annotation", "text")],
+            &[
+                "0800 - 0900 # # 42",
+                "0900 - 0930 # 42",
+                "1000 - 1030 # 42 # 7",
+            ],
+        );
+        let options = Options::from_data(&data).unwrap();
+        let report = data.report(&options);
+        assert_eq!(
+            lines(&report),
+            [(0, "/ 42", 60), (0, "42", 30), (0, "42 / 7", 30)]
+        );
+        let keys: Vec<GroupKey> = report.lines.iter().map(|line| line.row.key()).collect();
+        assert_eq!(
+            keys,
+            [
+                GroupKey {
+                    tags: vec![],
+                    annotation: Some(String::from("42")),
+                },
+                GroupKey {
+                    tags: vec![String::from("42")],
+                    annotation: None,
+                },
+                GroupKey {
+                    tags: vec![String::from("42")],
+                    annotation: Some(String::from("7")),
+                },
+            ]
+        );
+        keys.iter()
+            .for_each(|key| assert_eq!(GroupKey::parse(&key.title()), *key));
+    }
+
+    #[test]
+    fn nests_annotation_keys_below_tree_paths() {
+        let data = test_data(
+            &[("grouping", "tree"), ("annotation", "/#(\\d+)/")],
+            &[
+                "0800 - 0900 # client:acme # fix #42",
+                "0900 - 0930 # client:acme # #7",
+                "0930 - 1000 # # #42",
+            ],
+        );
+        let options = Options::from_data(&data).unwrap();
+        assert_eq!(
+            lines(&data.report(&options)),
+            [
+                (0, "client", 90),
+                (1, "client:acme", 90),
+                (2, "client:acme / 42", 60),
+                (2, "client:acme / 7", 30),
+                (0, "", 30),
+                (1, "/ 42", 30),
+            ]
+        );
+    }
+}
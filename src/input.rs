@@ -0,0 +1,485 @@
+//! The timewarrior extension input format.
+
+use crate::error::Error;
+use crate::timewarrior_datetime;
+use crate::timezone::ReportTimezone;
+use chrono::prelude::*;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::collections::HashMap;
+use std::io::BufRead;
+
+fn date_time_to_date_string(datetime: DateTime<FixedOffset>) -> String {
+    datetime.date_naive().format("%Y-%m-%d").to_string()
+}
+
+/// The raw value of a header setting.
+#[derive(Debug)]
+pub struct Value(pub String);
+
+impl Value {
+    /// Parses the value as a timewarrior timestamp in `timezone`.
+    pub fn value_to_date_time(
+        &self,
+        timezone: &ReportTimezone,
+    ) -> chrono::ParseResult<DateTime<FixedOffset>> {
+        timewarrior_datetime::parse_in(&self.0[..], timezone)
+    }
+
+    /// Interprets the value the way timewarrior reads boolean settings.
+    pub fn value_to_bool(&self) -> bool {
+        matches!(
+            self.0.trim().to_lowercase().as_str(),
+            "on" | "yes" | "y" | "true" | "1"
+        )
+    }
+}
+
+/// One tracked interval. `end` is `None` while the interval is still running.
+#[derive(Debug, Deserialize)]
+pub struct Interval {
+    #[serde(with = "timewarrior_datetime")]
+    pub start: DateTime<FixedOffset>,
+    #[serde(default)]
+    #[serde(with = "timewarrior_datetime::option")]
+    pub end: Option<DateTime<FixedOffset>>,
+    pub tags: Vec<String>,
+    pub annotation: Option<String>,
+}
+
+impl Interval {
+    /// Re-expresses the interval's timestamps in `timezone`.
+    pub fn with_timezone(self, timezone: &ReportTimezone) -> Interval {
+        Interval {
+            start: timezone.convert(&self.start),
+            end: self.end.map(|end| timezone.convert(&end)),
+            ..self
+        }
+    }
+
+    /// Whether the interval is still being tracked.
+    pub fn is_running(&self) -> bool {
+        self.end.is_none()
+    }
+
+    /// The part of the interval that falls inside `range`, or `None` if
+    /// there is none. A running interval is taken to end at `range.now`,
+    /// unless the range excludes running intervals.
+    pub fn clipped_bounds(
+        &self,
+        range: &ReportRange,
+    ) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        let start = match range.start {
+            Some(start) if start > self.start => start,
+            _ => self.start,
+        };
+        let end = match self.end {
+            Some(end) => end,
+            None if range.exclude_running => return None,
+            None => range.now.unwrap_or_else(|| Local::now().fixed_offset()),
+        };
+        let end = match range.end {
+            Some(range_end) if range_end < end => range_end,
+            _ => end,
+        };
+        (end > start).then_some((start, end))
+    }
+
+    /// The length of [`Interval::clipped_bounds`].
+    pub fn clipped_duration(&self, range: &ReportRange) -> chrono::Duration {
+        self.clipped_bounds(range)
+            .map_or(chrono::Duration::zero(), |(start, end)| {
+                end.signed_duration_since(start)
+            })
+    }
+
+    /// The interval's tags joined in their recorded order.
+    pub fn title(&self) -> String {
+        self.tags.join(", ")
+    }
+}
+
+/// The report window from `temp.report.start` and `temp.report.end`. Either
+/// bound is `None` when timewarrior leaves it open, e.g. for `:all`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReportRange {
+    pub start: Option<DateTime<FixedOffset>>,
+    pub end: Option<DateTime<FixedOffset>>,
+    /// Where running intervals end, before clipping to `end`; the system
+    /// clock when unset.
+    pub now: Option<DateTime<FixedOffset>>,
+    /// Whether running intervals are left out altogether.
+    pub exclude_running: bool,
+}
+
+/// The decoded extension input: the header settings and the intervals.
+#[derive(Debug)]
+pub struct Data {
+    pub settings: HashMap<String, Value>,
+    pub intervals: Vec<Interval>,
+    pub timezone: ReportTimezone,
+    pub range: ReportRange,
+    /// Intervals skipped in lenient mode, with the reason.
+    pub warnings: Vec<Error>,
+    /// The previous period, when the report is compared with it.
+    pub previous: Option<Box<Data>>,
+    /// The intervals [`Data::apply_tag_rules`] dropped, with their recorded
+    /// tags. They are left out of the report but still count as tracked
+    /// time in the quality checks.
+    pub filtered: Vec<Interval>,
+    /// The time the report is made at, from `reports.grouped.now` or the
+    /// system clock. Running intervals end here.
+    pub now: DateTime<FixedOffset>,
+}
+
+impl Data {
+    /// Builds the data from settings and intervals read from any source.
+    /// The intervals are converted to the zone in `reports.grouped.timezone`
+    /// and the range is taken from `temp.report.start` and
+    /// `temp.report.end`. `reports.grouped.now` fixes the current time, as
+    /// a timewarrior or RFC 3339 timestamp, and `reports.grouped.running`
+    /// set to `exclude` leaves running intervals out.
+    pub fn new(
+        settings: HashMap<String, Value>,
+        intervals: Vec<Interval>,
+        warnings: Vec<Error>,
+    ) -> Result<Data, Error> {
+        let timezone = match settings.get("reports.grouped.timezone") {
+            Some(value) => ReportTimezone::parse(&value.0).map_err(|message| Error::Setting {
+                name: "reports.grouped.timezone".into(),
+                value: value.0.clone(),
+                message,
+            })?,
+            None => ReportTimezone::default(),
+        };
+        let mut data = Data {
+            settings,
+            intervals: intervals
+                .into_iter()
+                .map(|interval| interval.with_timezone(&timezone))
+                .collect(),
+            timezone,
+            range: ReportRange::default(),
+            warnings,
+            previous: None,
+            filtered: vec![],
+            now: timezone.localize(&Utc::now().naive_utc()),
+        };
+        if let Some(value) = data
+            .find_setting("reports.grouped.now")
+            .filter(|value| !value.0.trim().is_empty())
+        {
+            let text = value.0.trim();
+            data.now =
+                match DateTime::parse_from_rfc3339(text) {
+                    Ok(now) => data.timezone.convert(&now),
+                    Err(_) => value.value_to_date_time(&data.timezone).map_err(|source| {
+                        Error::Timestamp {
+                            name: "reports.grouped.now".into(),
+                            text: text.to_string(),
+                            source,
+                        }
+                    })?,
+                };
+        }
+        let exclude_running = match data.find_setting("reports.grouped.running") {
+            None => false,
+            Some(value) => match value.0.trim() {
+                "" | "include" => false,
+                "exclude" => true,
+                text => {
+                    return Err(Error::Setting {
+                        name: "reports.grouped.running".into(),
+                        value: text.to_string(),
+                        message: "expected 'include' or 'exclude'".into(),
+                    })
+                }
+            },
+        };
+        data.range = ReportRange {
+            start: data.find_date_time_setting("temp.report.start")?,
+            end: data.find_date_time_setting("temp.report.end")?,
+            now: Some(data.now),
+            exclude_running,
+        };
+        Ok(data)
+    }
+
+    /// The report's dates, e.g. `2023-10-09 - 2023-10-15`, or an empty
+    /// string for an open range.
+    pub fn report_title(&self) -> String {
+        match self.report_range() {
+            ReportRange {
+                start: Some(start),
+                end: Some(end),
+                ..
+            } => format!(
+                "{} - {}",
+                date_time_to_date_string(start),
+                date_time_to_date_string(
+                    end.checked_sub_signed(chrono::Duration::seconds(1))
+                        .unwrap()
+                ),
+            ),
+            _ => String::from(""),
+        }
+    }
+
+    /// The window intervals are clipped to.
+    pub fn report_range(&self) -> ReportRange {
+        self.range
+    }
+
+    /// The interval that is still being tracked, if it counts towards the
+    /// report.
+    pub fn running_interval(&self) -> Option<&Interval> {
+        let range = self.report_range();
+        self.intervals
+            .iter()
+            .rev()
+            .find(|interval| interval.is_running() && interval.clipped_bounds(&range).is_some())
+    }
+
+    /// The value of a header setting, or of the command-line option that
+    /// overrode it.
+    pub fn find_setting(&self, name: &str) -> Option<&Value> {
+        self.settings.get(name)
+    }
+
+    fn find_date_time_setting(&self, name: &str) -> Result<Option<DateTime<FixedOffset>>, Error> {
+        match self.find_setting(name).filter(|value| !value.0.is_empty()) {
+            Some(value) => value
+                .value_to_date_time(&self.timezone)
+                .map(Some)
+                .map_err(|source| Error::Timestamp {
+                    name: name.into(),
+                    text: value.0.clone(),
+                    source,
+                }),
+            None => Ok(None),
+        }
+    }
+}
+
+// Converts a byte offset into `text` to a 1-based line and column.
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = offset - before.rfind('\n').map_or(0, |index| index + 1) + 1;
+    (line, column)
+}
+
+fn json_error(source: serde_json::Error, lines: &[String], line: usize, column: usize) -> Error {
+    Error::Json {
+        line,
+        column,
+        text: lines
+            .get(line.saturating_sub(1))
+            .cloned()
+            .unwrap_or_default(),
+        source,
+    }
+}
+
+/// Reads the extension protocol: `name: value` header lines, a blank line
+/// and the intervals as a JSON array. `overrides` replace header settings.
+///
+/// With `reports.grouped.lenient` set, intervals that cannot be decoded are
+/// skipped and recorded in [`Data::warnings`] instead of failing the read.
+pub fn read_data(reader: impl BufRead, overrides: &[(String, String)]) -> Result<Data, Error> {
+    let lines = reader.lines().collect::<Result<Vec<String>, _>>()?;
+
+    let header_end = lines
+        .iter()
+        .position(|line| line.trim().is_empty() || line.starts_with('['))
+        .unwrap_or(lines.len());
+    let mut settings = HashMap::new();
+    for (index, line) in lines[..header_end].iter().enumerate() {
+        let (key, value) = match line.find(": ") {
+            Some(separator_index) => (&line[..separator_index], &line[(separator_index + 2)..]),
+            None => match line.strip_suffix(':') {
+                Some(key) => (key, ""),
+                None => {
+                    return Err(Error::Header {
+                        line: index + 1,
+                        text: line.clone(),
+                    })
+                }
+            },
+        };
+        settings.insert(key.to_string(), Value(value.to_string()));
+    }
+    overrides.iter().for_each(|(key, value)| {
+        settings.insert(key.clone(), Value(value.clone()));
+    });
+
+    let lenient = settings
+        .get("reports.grouped.lenient")
+        .is_some_and(Value::value_to_bool);
+
+    let json_start = lines[header_end..]
+        .iter()
+        .position(|line| !line.trim().is_empty())
+        .map_or(lines.len(), |index| header_end + index);
+    let json = lines[json_start..].join("\n");
+    let raw_intervals: Vec<&RawValue> = serde_json::from_str(&json).map_err(|error| {
+        let (line, column) = (error.line() + json_start, error.column());
+        json_error(error, &lines, line, column)
+    })?;
+
+    let mut intervals = vec![];
+    let mut warnings = vec![];
+    for raw_interval in raw_intervals {
+        let raw = raw_interval.get();
+        match serde_json::from_str::<Interval>(raw) {
+            Ok(interval) => intervals.push(interval),
+            Err(error) => {
+                let offset = raw.as_ptr() as usize - json.as_ptr() as usize;
+                let (line, column) = line_and_column(&json, offset);
+                let column = match error.line() {
+                    1 => column + error.column() - 1,
+                    _ => error.column(),
+                };
+                let line = line + json_start + error.line() - 1;
+                let error = json_error(error, &lines, line, column);
+                if !lenient {
+                    return Err(error);
+                }
+                warnings.push(error);
+            }
+        }
+    }
+
+    Data::new(settings, intervals, warnings)
+}
+
+/// Data for tests: a report of 2023-10-15 in UTC, made at noon, with
+/// `reports.grouped.<name>` `settings` (`temp.*` names are kept as they
+/// are) and intervals written like data file lines without `inc`, e.g.
+/// `0800 - 0930 # api code # fix 42`. Times are on 2023-10-15 unless they
+/// carry a date, as in `1014T0800`.
+#[cfg(test)]
+pub(crate) fn test_data(settings: &[(&str, &str)], intervals: &[&str]) -> Data {
+    let mut all = HashMap::new();
+    [
+        ("temp.report.start", "20231015T000000Z"),
+        ("temp.report.end", "20231016T000000Z"),
+        ("timezone", "UTC"),
+        ("now", "20231015T120000Z"),
+        ("color", "off"),
+        ("width", "0"),
+    ]
+    .iter()
+    .chain(settings)
+    .for_each(|(name, value)| {
+        let name = match name.starts_with("temp.") {
+            true => name.to_string(),
+            false => format!("reports.grouped.{}", name),
+        };
+        all.insert(name, Value(value.to_string()));
+    });
+    let timestamp = |time: &str| match time.trim().contains('T') {
+        true => format!("2023{}00Z", time.trim()),
+        false => format!("20231015T{}00Z", time.trim()),
+    };
+    let intervals = intervals
+        .iter()
+        .map(|line| {
+            let (times, rest) = match line.split_once(" #") {
+                Some((times, rest)) => (times, format!(" #{}", rest)),
+                None => (*line, String::new()),
+            };
+            let times: Vec<String> = times.split(" - ").map(timestamp).collect();
+            crate::database::parse_interval(&format!("inc {}{}", times.join(" - "), rest)).unwrap()
+        })
+        .collect();
+    Data::new(all, intervals, vec![]).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<FixedOffset> {
+        Utc.with_ymd_and_hms(2023, 10, 15, hour, minute, 0)
+            .unwrap()
+            .fixed_offset()
+    }
+
+    fn interval(start: DateTime<FixedOffset>, end: Option<DateTime<FixedOffset>>) -> Interval {
+        Interval {
+            start,
+            end,
+            tags: vec![],
+            annotation: None,
+        }
+    }
+
+    fn range(start: u32, end: u32) -> ReportRange {
+        ReportRange {
+            start: Some(at(start, 0)),
+            end: Some(at(end, 0)),
+            now: Some(at(12, 0)),
+            exclude_running: false,
+        }
+    }
+
+    #[test]
+    fn clips_intervals_to_the_range() {
+        let range = range(9, 17);
+        assert_eq!(
+            interval(at(8, 0), Some(at(10, 0))).clipped_bounds(&range),
+            Some((at(9, 0), at(10, 0)))
+        );
+        assert_eq!(
+            interval(at(16, 0), Some(at(18, 30))).clipped_bounds(&range),
+            Some((at(16, 0), at(17, 0)))
+        );
+        assert_eq!(
+            interval(at(10, 0), Some(at(11, 15))).clipped_duration(&range),
+            chrono::Duration::minutes(75)
+        );
+    }
+
+    #[test]
+    fn leaves_out_intervals_outside_the_range() {
+        let range = range(9, 17);
+        assert_eq!(
+            interval(at(7, 0), Some(at(9, 0))).clipped_bounds(&range),
+            None
+        );
+        assert_eq!(
+            interval(at(17, 0), Some(at(18, 0))).clipped_bounds(&range),
+            None
+        );
+        assert_eq!(
+            interval(at(17, 0), Some(at(18, 0))).clipped_duration(&range),
+            chrono::Duration::zero()
+        );
+    }
+
+    #[test]
+    fn ends_running_intervals_at_now() {
+        let running = interval(at(11, 0), None);
+        assert_eq!(
+            running.clipped_bounds(&range(9, 17)),
+            Some((at(11, 0), at(12, 0)))
+        );
+        assert_eq!(running.clipped_bounds(&range(9, 11)), None);
+        let later = ReportRange {
+            now: Some(at(18, 0)),
+            ..range(9, 17)
+        };
+        assert_eq!(running.clipped_bounds(&later), Some((at(11, 0), at(17, 0))));
+        let open = ReportRange {
+            end: None,
+            ..range(9, 17)
+        };
+        assert_eq!(running.clipped_bounds(&open), Some((at(11, 0), at(12, 0))));
+        let excluded = ReportRange {
+            exclude_running: true,
+            ..range(9, 17)
+        };
+        assert_eq!(running.clipped_bounds(&excluded), None);
+    }
+}
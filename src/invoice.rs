@@ -0,0 +1,264 @@
+//! Invoices built from the billed report lines.
+
+use crate::database;
+use crate::error::Error;
+use crate::input::Data;
+use crate::options::Options;
+use chrono::NaiveDate;
+use std::path::PathBuf;
+
+/// The file in timewarrior's database directory that holds the last
+/// invoice number handed out.
+pub const STATE_FILE: &str = "grouped-invoice-number";
+
+/// Invoice settings, from `reports.grouped.invoice.*`.
+#[derive(Debug, Clone, Default)]
+pub struct InvoiceConfig {
+    /// Who the invoice is from: a name followed by address lines.
+    pub from: Vec<String>,
+    /// Who the invoice is to: a name followed by address lines.
+    pub client: Vec<String>,
+    /// Tax in percent of the subtotal.
+    pub tax_rate: Option<f64>,
+    pub tax_label: String,
+    /// A fixed invoice number. When unset, the next number of the sequence
+    /// in the state file is used.
+    pub number: Option<String>,
+    pub number_prefix: String,
+    /// Where the sequence is kept, overriding the default in timewarrior's
+    /// database directory.
+    pub state: Option<PathBuf>,
+    /// Days until payment is due.
+    pub due_days: Option<i64>,
+    pub notes: Option<String>,
+}
+
+impl InvoiceConfig {
+    /// The file the invoice number sequence is kept in: `invoice.state`,
+    /// or [`STATE_FILE`] in the database directory from
+    /// [`database::path_for`]. Fails when there is no database directory
+    /// either.
+    pub fn state_path(&self, data: &Data) -> Result<PathBuf, Error> {
+        if let Some(path) = &self.state {
+            return Ok(path.clone());
+        }
+        match database::path_for(&data.settings) {
+            Some(directory) => Ok(directory.join(STATE_FILE)),
+            None => Err(Error::Argument(String::from(
+                "cannot find timewarrior's database to keep the invoice number in, set --invoice.state=PATH",
+            ))),
+        }
+    }
+
+    /// The invoice number to use: the configured one, or the number after
+    /// the one in the state file. A missing state file starts the sequence
+    /// at 1. The state file is left alone until [`InvoiceConfig::save_number`]
+    /// is called, so previews and failed runs don't use up numbers.
+    pub fn next_number(&self, data: &Data) -> Result<InvoiceNumber, Error> {
+        if let Some(number) = &self.number {
+            return Ok(InvoiceNumber {
+                number: number.clone(),
+                sequence: None,
+            });
+        }
+        let path = self.state_path(data)?;
+        let last = match std::fs::read_to_string(&path) {
+            Ok(text) => text.trim().parse::<u64>().map_err(|_| Error::File {
+                path: path.clone(),
+                source: std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("expected an invoice number, got '{}'", text.trim()),
+                ),
+            })?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(source) => return Err(Error::File { path, source }),
+        };
+        let sequence = last + 1;
+        Ok(InvoiceNumber {
+            number: format!("{}{:04}", self.number_prefix, sequence),
+            sequence: Some(sequence),
+        })
+    }
+
+    /// Records `number` as the last one handed out, once its invoice has
+    /// been written. Configured numbers are not recorded.
+    pub fn save_number(&self, data: &Data, number: &InvoiceNumber) -> Result<(), Error> {
+        let Some(sequence) = number.sequence else {
+            return Ok(());
+        };
+        let path = self.state_path(data)?;
+        std::fs::write(&path, format!("{}\n", sequence))
+            .map_err(|source| Error::File { path, source })
+    }
+}
+
+/// An invoice number from [`InvoiceConfig::next_number`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvoiceNumber {
+    pub number: String,
+    /// The number's place in the state file's sequence; `None` for a
+    /// configured number.
+    pub sequence: Option<u64>,
+}
+
+/// One billed group.
+#[derive(Debug, Clone)]
+pub struct LineItem {
+    pub description: String,
+    pub hours: f64,
+    pub rate: f64,
+    pub amount: f64,
+}
+
+/// Everything an invoice document shows. Amounts are rounded to cents, so
+/// the totals add up to what is printed.
+#[derive(Debug, Clone)]
+pub struct Invoice {
+    pub number: String,
+    pub date: NaiveDate,
+    pub due: Option<NaiveDate>,
+    /// The report range, as in [`Data::report_title`].
+    pub period: String,
+    pub from: Vec<String>,
+    pub client: Vec<String>,
+    pub currency: String,
+    pub items: Vec<LineItem>,
+    pub subtotal: f64,
+    pub tax_label: String,
+    pub tax_rate: Option<f64>,
+    pub tax: f64,
+    pub total: f64,
+    pub notes: Option<String>,
+}
+
+fn cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+impl Invoice {
+    /// Builds the invoice for the billable items of the report's bill,
+    /// dated `date`. Every interval is billed under a single item, so the
+    /// invoice never charges the same time twice, whatever the grouping.
+    pub fn new(
+        data: &Data,
+        options: &Options,
+        config: &InvoiceConfig,
+        number: String,
+        date: NaiveDate,
+    ) -> Invoice {
+        let billing = options.billing.clone().unwrap_or_default();
+        let report = data.report(options);
+        let bill = billing.bill(data, &report, options);
+        let items: Vec<LineItem> = bill
+            .items
+            .iter()
+            .filter(|(_, billed)| !billed.excluded && !billed.billable.is_zero())
+            .map(|(title, billed)| LineItem {
+                description: match title.as_str() {
+                    "" => String::from("(untagged)"),
+                    title => title.to_string(),
+                },
+                hours: billed.billable.num_seconds() as f64 / 3600.0,
+                rate: billed.rate.unwrap_or(0.0),
+                amount: cents(billed.amount),
+            })
+            .collect();
+        let subtotal = cents(items.iter().map(|item| item.amount).sum());
+        let tax = cents(subtotal * config.tax_rate.unwrap_or(0.0) / 100.0);
+        Invoice {
+            number,
+            date,
+            due: config
+                .due_days
+                .map(|days| date + chrono::Duration::days(days)),
+            period: data.report_title(),
+            from: config.from.clone(),
+            client: config.client.clone(),
+            currency: billing.currency,
+            items,
+            subtotal,
+            tax_label: config.tax_label.clone(),
+            tax_rate: config.tax_rate,
+            tax,
+            total: cents(subtotal + tax),
+            notes: config.notes.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::test_data;
+
+    #[test]
+    fn invoices_overlapping_groupings_once_per_interval() {
+        for grouping in ["tag", "tree"] {
+            let data = test_data(
+                &[
+                    ("grouping", grouping),
+                    ("rate", "100"),
+                    ("rate.client:*", "120"),
+                    ("billing.exclude", "internal"),
+                ],
+                &[
+                    "0800 - 0930 # client:acme:api code",
+                    "0930 - 1000 #",
+                    "1000 - 1030 # code internal",
+                ],
+            );
+            let options = Options::from_data(&data).unwrap();
+            let config = InvoiceConfig {
+                tax_rate: Some(10.0),
+                ..InvoiceConfig::default()
+            };
+            let date = NaiveDate::from_ymd_opt(2023, 10, 31).unwrap();
+            let invoice = Invoice::new(&data, &options, &config, String::from("7"), date);
+            let items: Vec<(&str, f64, f64)> = invoice
+                .items
+                .iter()
+                .map(|item| (item.description.as_str(), item.hours, item.amount))
+                .collect();
+            assert_eq!(
+                items,
+                [("client:acme:api", 1.5, 180.0), ("(untagged)", 0.5, 50.0)],
+                "{}",
+                grouping
+            );
+            assert_eq!(invoice.subtotal, 230.0);
+            assert_eq!(invoice.tax, 23.0);
+            assert_eq!(invoice.total, 253.0);
+        }
+    }
+
+    #[test]
+    fn keeps_the_number_sequence_in_the_state_file() {
+        let path = std::env::temp_dir().join(format!(
+            "timewarrior-grouped-invoice-{}",
+            std::process::id()
+        ));
+        let config = InvoiceConfig {
+            number_prefix: String::from("INV-"),
+            state: Some(path.clone()),
+            ..InvoiceConfig::default()
+        };
+        let data = test_data(&[], &[]);
+        let first = config.next_number(&data).unwrap();
+        assert_eq!(first.number, "INV-0001");
+        // Numbers are only used up once saved.
+        assert_eq!(config.next_number(&data).unwrap(), first);
+        config.save_number(&data, &first).unwrap();
+        let second = config.next_number(&data);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(second.unwrap().number, "INV-0002");
+
+        let fixed = InvoiceConfig {
+            number: Some(String::from("2023/1")),
+            ..config
+        };
+        let number = fixed.next_number(&data).unwrap();
+        assert_eq!(number.sequence, None);
+        fixed.save_number(&data, &number).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,48 @@
+//! Parsing, grouping and rendering for the `grouped` timewarrior report.
+//!
+//! timewarrior runs report extensions with its settings and the intervals
+//! of the requested range on stdin. [`read_data`] decodes that input,
+//! [`Data::report`] groups the intervals into the table every renderer
+//! shares, by tag combination, tag, tag tree, category rule or annotation,
+//! and [`render::render`] writes the report. The tag aliases and filters in
+//! the options change the intervals themselves, so they are applied to the
+//! data with [`Data::apply_tag_rules`] before rendering:
+//!
+//! ```no_run
+//! use timewarrior_grouped::{read_data, render, Options};
+//!
+//! let mut data = read_data(std::io::stdin().lock(), &[]).unwrap();
+//! let options = Options::from_data(&data).unwrap();
+//! data.apply_tag_rules(&options.tags);
+//! render::render(&data, &options, &mut std::io::stdout()).unwrap();
+//! ```
+//!
+//! Outside of timewarrior, [`database::Database::read`] builds the same
+//! [`Data`] from timewarrior's database directory.
+
+pub mod annotation;
+pub mod billing;
+pub mod category;
+pub mod compare;
+pub mod database;
+pub mod distribution;
+pub mod error;
+pub mod filter;
+pub mod goal;
+pub mod group;
+pub mod input;
+pub mod invoice;
+pub mod options;
+pub mod pattern;
+pub mod pivot;
+pub mod quality;
+pub mod render;
+pub mod theme;
+pub mod timewarrior_datetime;
+pub mod timezone;
+
+pub use error::Error;
+pub use group::GroupReportRow;
+pub use input::{read_data, Data, Interval, ReportRange, Value};
+pub use options::Options;
+pub use timezone::ReportTimezone;
@@ -1,277 +1,114 @@
-use chrono::prelude::*;
-use colored::*;
-use serde::Deserialize;
-use std::collections::HashMap;
-
-mod timewarrior_datetime {
-    use chrono::{DateTime, Local, NaiveDateTime, ParseResult};
-    use serde::{self, Deserialize, Deserializer};
-
-    const FORMAT: &str = "%Y%m%dT%H%M%SZ";
-
-    pub fn parse(s: &str) -> ParseResult<DateTime<Local>> {
-        let dt = NaiveDateTime::parse_from_str(s, FORMAT)?;
-        Ok(DateTime::<Local>::from_naive_utc_and_offset(
-            dt,
-            *Local::now().offset(),
-        ))
-    }
-
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Local>, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        parse(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
-    }
+use std::io::Write;
+use timewarrior_grouped::compare::read_previous;
+use timewarrior_grouped::database::{self, Database};
+use timewarrior_grouped::invoice::Invoice;
+use timewarrior_grouped::options::Compare;
+use timewarrior_grouped::{options, read_data, render, Error, Options, Value};
+
+fn exit_with_error(error: Error) -> ! {
+    eprintln!("timewarrior-grouped: {}", error);
+    std::process::exit(1);
 }
 
-mod timewarrior_datetime_option {
-    use chrono::{DateTime, Local, NaiveDateTime, ParseResult};
-    use serde::{self, Deserialize, Deserializer};
-
-    const FORMAT: &str = "%Y%m%dT%H%M%SZ";
-
-    pub fn parse(s: &str) -> ParseResult<Option<DateTime<Local>>> {
-        let dt = NaiveDateTime::parse_from_str(s, FORMAT)?;
-        Ok(Some(DateTime::<Local>::from_naive_utc_and_offset(
-            dt,
-            *Local::now().offset(),
-        )))
-    }
-
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Local>>, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        parse(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        print!("{}", options::usage());
+        return;
     }
-}
+    let overrides = options::parse_args(&args).unwrap_or_else(|error| exit_with_error(error));
 
-fn date_time_to_date_string(datetime: DateTime<Local>) -> String {
-    datetime.date_naive().format("%Y-%m-%d").to_string()
-}
-
-fn pad_string(s: &str, len: usize) -> String {
-    match len.checked_sub(s.len()) {
-        Some(padding) => {
-            let mut padded_string = String::with_capacity(len);
-            for _ in 0..padding {
-                padded_string.push(' ');
-            }
-            padded_string.push_str(s);
-            padded_string
+    let database = overrides
+        .iter()
+        .find(|(key, _)| key == "reports.grouped.database")
+        .filter(|(_, value)| value.as_str() != "off");
+    let mut data = match database {
+        Some(_) => {
+            let settings = overrides
+                .iter()
+                .map(|(key, value)| (key.clone(), Value(value.clone())))
+                .collect();
+            let path = database::path_for(&settings).unwrap_or_else(|| {
+                exit_with_error(Error::Argument(String::from(
+                    "cannot find timewarrior's database, set --database=PATH",
+                )))
+            });
+            Database::new(path).read(&overrides)
         }
-        None => s.to_string(),
+        None => read_data(std::io::stdin().lock(), &overrides),
     }
-}
-
-#[derive(Debug)]
-struct Value(String);
-
-impl Value {
-    pub fn value_to_date_time(&self) -> DateTime<Local> {
-        timewarrior_datetime::parse(&self.0[..]).unwrap()
-    }
-}
-
-#[derive(Debug, Deserialize)]
-struct Interval {
-    #[serde(with = "timewarrior_datetime")]
-    start: DateTime<Local>,
-    #[serde(default)]
-    #[serde(with = "timewarrior_datetime_option")]
-    end: Option<DateTime<Local>>,
-    tags: Vec<String>,
-    annotation: Option<String>,
-}
-
-impl Interval {
-    pub fn duration(&self) -> chrono::Duration {
-        self.end
-            .unwrap_or(Local::now())
-            .signed_duration_since(self.start)
-    }
-
-    pub fn title(&self) -> String {
-        self.tags.join(", ")
-    }
-}
-
-#[derive(Debug)]
-struct GroupReportRow {
-    title: String,
-    duration: chrono::Duration,
-}
-
-impl GroupReportRow {
-    pub fn padded_title(&self, len: usize) -> String {
-        pad_string(&self.title, len)
-    }
-}
-
-#[derive(Debug)]
-struct Data {
-    settings: HashMap<String, Value>,
-    intervals: Vec<Interval>,
-}
-
-impl Data {
-    pub fn report_title(&self) -> String {
-        let start = self.find_setting("temp.report.start");
-        let end = self.find_setting("temp.report.end");
-        if start.is_some() && !start.unwrap().0.is_empty() && end.is_some() {
-            format!(
-                "{} - {}",
-                date_time_to_date_string(start.unwrap().value_to_date_time()),
-                date_time_to_date_string(
-                    end.unwrap()
-                        .value_to_date_time()
-                        .checked_sub_signed(chrono::Duration::seconds(1))
-                        .unwrap()
-                ),
-            )
-        } else {
-            String::from("")
+    .unwrap_or_else(|error| exit_with_error(error));
+    data.warnings.iter().for_each(|warning| {
+        eprintln!("timewarrior-grouped: skipping interval: {}", warning);
+    });
+    let options = Options::from_data(&data).unwrap_or_else(|error| exit_with_error(error));
+    colored::control::set_override(options.color);
+
+    if let Some(compare) = &options.compare {
+        let previous = match compare {
+            Compare::File(path) => std::fs::File::open(path)
+                .map_err(|source| Error::File {
+                    path: path.clone(),
+                    source,
+                })
+                .and_then(|file| read_previous(&data, std::io::BufReader::new(file))),
+            Compare::Database => match database::path_for(&data.settings) {
+                Some(path) => Database::new(path).read_previous(&data),
+                None => Err(Error::Argument(String::from(
+                    "cannot find timewarrior's database, set --database=PATH",
+                ))),
+            },
         }
-    }
-
-    pub fn find_setting(&self, name: &str) -> Option<&Value> {
-        self.settings.get(name)
-    }
-
-    pub fn grouped_report_rows(&self) -> Vec<GroupReportRow> {
-        let mut rows: Vec<GroupReportRow> = vec![];
-        self.intervals.iter().for_each(|interval| {
-            let title = interval.title();
-            let row = rows.iter_mut().find(|row| row.title == title);
-            match row {
-                Some(row) => {
-                    row.duration = row.duration.checked_add(&interval.duration()).unwrap();
-                }
-                None => rows.push(GroupReportRow {
-                    title,
-                    duration: interval.duration(),
-                }),
-            };
+        .unwrap_or_else(|error| exit_with_error(error));
+        previous.warnings.iter().for_each(|warning| {
+            eprintln!("timewarrior-grouped: skipping interval: {}", warning);
         });
-        rows
-    }
-}
-
-fn get_data() -> Data {
-    let mut settings = HashMap::new();
-    let mut interval_lines = vec![];
-    std::io::stdin().lines().for_each(|line| {
-        let line = line.unwrap();
-        let separator_index = line.find(": ");
-        if let Some(separator_index) = separator_index {
-            let key = line[0..separator_index].into();
-            let value = Value(line[(separator_index + 2)..].into());
-            settings.insert(key, value);
-        } else if line != "\n" {
-            interval_lines.push(line);
-        }
-    });
-    let intervals: Vec<Interval> = serde_json::from_str(&interval_lines.join("")).unwrap();
-    Data {
-        settings,
-        intervals,
+        data.previous = Some(Box::new(previous));
     }
-}
-
-const MINIMUM_TAGS_WIDTH: usize = 12;
-
-fn main() {
-    colored::control::set_override(true);
-
-    let data = get_data();
-    println!("{}", data.report_title().dimmed());
-    println!();
-    let mut rows = data.grouped_report_rows();
-    let mut lengths = rows
-        .iter()
-        .map(|row| row.title.len())
-        .collect::<Vec<usize>>();
-    lengths.extend([MINIMUM_TAGS_WIDTH].iter());
-    let max_title = lengths.into_iter().max().unwrap_or(0);
-    let mut total_duration = chrono::Duration::zero();
-    rows.iter().for_each(|row| {
-        total_duration = total_duration.checked_add(&row.duration).unwrap();
-    });
-
-    println!(
-        "{}",
-        format!(
-            "{} {:>10} {:>10} {:>5}",
-            pad_string("TAGS", max_title),
-            "MINUTES",
-            "HOURS",
-            "%"
-        )
-        .bold()
-        .underline()
-    );
-
-    rows.sort_by_key(|r| r.duration);
-    let mut it = rows.iter().rev().peekable();
-    while let Some(row) = it.next() {
-        let mut string: ColoredString = format!(
-            "{} {:>10} {:10.1} {:5.0}",
-            row.padded_title(max_title),
-            row.duration.num_minutes(),
-            row.duration.num_seconds() as f64 / 3600.0,
-            row.duration.num_minutes() as f64 / (total_duration.num_minutes() as f64) * 100.0,
-        )
-        .normal();
-        if row.title.eq("code") {
-            string = string.color(Color::Blue);
+    data.apply_tag_rules(&options.tags);
+
+    let mut out = std::io::stdout().lock();
+    let mut invoice_number = None;
+    let result = match &options.invoice {
+        _ if options.explain => render::explain::render(&data, &options, &mut out),
+        Some(config) => {
+            let number = config
+                .next_number(&data)
+                .unwrap_or_else(|error| exit_with_error(error));
+            let invoice = Invoice::new(
+                &data,
+                &options,
+                config,
+                number.number.clone(),
+                data.now.date_naive(),
+            );
+            invoice_number = Some((config, number));
+            render::invoice::render(&invoice, options.format, &mut out)
         }
-        if it.peek().is_none() {
-            string = string.underline();
+        None => render::render(&data, &options, &mut out),
+    };
+    match result.and_then(|_| out.flush()) {
+        // Only an invoice that was written in full uses up its number.
+        Ok(()) => {
+            if let Some((config, number)) = &invoice_number {
+                config
+                    .save_number(&data, number)
+                    .unwrap_or_else(|error| exit_with_error(error));
+            }
         }
-        println!("{}", string);
+        Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => {}
+        Err(error) => exit_with_error(Error::Io(error)),
     }
-    println!(
-        "{}",
-        format!(
-            "{} {:>10} {:10.1}",
-            pad_string("TOTAL", max_title),
-            total_duration.num_minutes(),
-            total_duration.num_seconds() as f64 / 3600.0,
-        )
-        .bold()
-    );
-
-    println!();
-    println!(
-        "{}",
-        format!(
-            "{} {:>10}",
-            pad_string("intervals", max_title),
-            data.intervals.len()
-        )
-        .dimmed()
-    );
 
-    let annotated_intervals: Vec<&Interval> = data
-        .intervals
-        .iter()
-        .filter(|interval| interval.annotation.is_some())
-        .collect();
-    if !annotated_intervals.is_empty() {
-        println!();
-        println!("{}", pad_string("annotations", max_title).dimmed());
-        annotated_intervals.iter().for_each(|interval| {
-            let string = format!(
-                "{} {:>10} {:10.1} {:5.0} {}",
-                pad_string(&interval.title(), max_title),
-                interval.duration().num_minutes(),
-                interval.duration().num_seconds() as f64 / 3600.0,
-                interval.duration().num_minutes() as f64 / (total_duration.num_minutes() as f64)
-                    * 100.0,
-                interval.annotation.as_ref().unwrap(),
+    if let Some(config) = options.quality.as_ref().filter(|config| config.fail) {
+        let quality = data.quality(config);
+        if !quality.is_clean() {
+            eprintln!(
+                "timewarrior-grouped: data quality check failed: {} overlaps, {} gaps",
+                quality.overlaps.len(),
+                quality.gaps.len()
             );
-            println!("{}", string.dimmed());
-        });
+            std::process::exit(2);
+        }
     }
 }
@@ -0,0 +1,1088 @@
+//! Report options, read from `reports.grouped.*` settings and command-line
+//! flags.
+
+use crate::annotation::AnnotationKey;
+use crate::billing::{Billing, Rounding};
+use crate::category::CategoryRule;
+use crate::distribution::DistributionConfig;
+use crate::error::Error;
+use crate::filter::TagRules;
+use crate::goal::Goal;
+use crate::input::{Data, Value};
+use crate::invoice::InvoiceConfig;
+use crate::pattern::{TagFilter, TagPattern};
+use crate::pivot::Period;
+use crate::quality::QualityConfig;
+use crate::render::terminal_width;
+use crate::theme::{auto_color, Palette, RowMatcher, Theme};
+use chrono::{Duration, NaiveTime, Weekday};
+use colored::Color;
+use regex::Regex;
+use std::path::PathBuf;
+
+pub const SETTINGS_PREFIX: &str = "reports.grouped.";
+
+// Every option can be set as a `reports.grouped.<name>` configuration
+// setting or overridden on the command line as `--<name>=<value>`.
+const SETTINGS: &[(&str, &str, &str)] = &[
+    (
+        "sort",
+        "duration|title",
+        "order of the table rows (default: duration)",
+    ),
+    ("reverse", "on|off", "reverse the sort order"),
+    (
+        "grouping",
+        "combination|unordered|tag|tree|annotation",
+        "group by ordered or unordered tag combination, by each tag, by a tag tree or by annotation (default: combination)",
+    ),
+    (
+        "annotation",
+        "off|text|word|/REGEX/",
+        "also group by the annotation, its first word or a regex's first capture, e.g. /#(\\d+)/ (default: off)",
+    ),
+    (
+        "include",
+        "TAG,...",
+        "only report intervals with one of these tags, globs or /regexes/",
+    ),
+    (
+        "exclude",
+        "TAG,...",
+        "leave out intervals with one of these tags, globs or /regexes/",
+    ),
+    (
+        "alias.<tag>",
+        "TAG",
+        "report a tag under another name, e.g. alias.js = javascript",
+    ),
+    (
+        "strip",
+        "TAG,...",
+        "remove these tags, globs or /regexes/ from titles but keep their time",
+    ),
+    (
+        "category.<N>",
+        "TAG,... -> NAME",
+        "group intervals with one of these tags, globs or /regexes/ as NAME; the lowest matching N wins",
+    ),
+    (
+        "explain",
+        "on|off",
+        "list which category rule every interval matched instead of the report",
+    ),
+    (
+        "tree.separator",
+        "SEP",
+        "splits tags into tree levels (default: :)",
+    ),
+    (
+        "tree.depth",
+        "N",
+        "deepest tree level to show, 0 for top-level only (default: all)",
+    ),
+    (
+        "pivot",
+        "off|day|week|month",
+        "one column per day, ISO week or month (default: off)",
+    ),
+    (
+        "min_width",
+        "N",
+        "minimum width of the tags column (default: 12)",
+    ),
+    (
+        "width",
+        "N",
+        "width to fit the output to, 0 for unlimited (default: terminal width)",
+    ),
+    (
+        "align",
+        "right|left",
+        "alignment of the titles (default: right, left for tree grouping)",
+    ),
+    (
+        "overflow",
+        "truncate|wrap",
+        "what to do with titles too long for the width (default: truncate)",
+    ),
+    (
+        "color",
+        "auto|on|off",
+        "colour the output; auto colours terminals unless NO_COLOR is set or timewarrior's color is off (default: auto)",
+    ),
+    (
+        "theme",
+        "default|dark|light|mono",
+        "colours for deltas, goals, data quality and secondary text (default: default)",
+    ),
+    (
+        "theme.<role>",
+        "COLOR|none",
+        "override one colour of the theme: good, bad, warning, accent or muted",
+    ),
+    (
+        "highlight.<tags>",
+        "COLOR|none",
+        "colour the row with exactly this title",
+    ),
+    (
+        "color.tag.<tag>",
+        "COLOR",
+        "colour rows with this tag, or a tag prefix written as prefix*",
+    ),
+    (
+        "color.regex.<regex>",
+        "COLOR",
+        "colour rows whose title matches this regular expression",
+    ),
+    (
+        "color.above.<percent>",
+        "COLOR",
+        "colour rows with more than this share of the total time",
+    ),
+    (
+        "billing",
+        "on|off",
+        "show billable hours and amounts (default: on when a rate is set)",
+    ),
+    ("rate", "N", "default hourly rate"),
+    (
+        "rate.<tag>",
+        "N",
+        "hourly rate for a tag, or for a tag prefix written as prefix*",
+    ),
+    (
+        "billing.increment",
+        "DURATION",
+        "round billable time up to this, e.g. 15min (default: none)",
+    ),
+    (
+        "billing.rounding",
+        "interval|group",
+        "round each interval or each group's total (default: group)",
+    ),
+    ("billing.currency", "TEXT", "currency shown with amounts"),
+    (
+        "billing.exclude",
+        "TAG,...",
+        "tags or prefix* patterns that are not billable",
+    ),
+    (
+        "invoice",
+        "on|off",
+        "print an invoice for the billed groups instead of the report",
+    ),
+    (
+        "invoice.from",
+        "LINE;...",
+        "your name and address, lines separated by ';'",
+    ),
+    (
+        "invoice.client",
+        "LINE;...",
+        "the client's name and address, lines separated by ';'",
+    ),
+    ("invoice.tax", "PERCENT", "tax added to the subtotal"),
+    ("invoice.tax.label", "TEXT", "name of the tax (default: Tax)"),
+    (
+        "invoice.number",
+        "TEXT",
+        "use this invoice number instead of the next in the sequence",
+    ),
+    (
+        "invoice.number.prefix",
+        "TEXT",
+        "put in front of sequence numbers, e.g. INV-",
+    ),
+    (
+        "invoice.state",
+        "PATH",
+        "file holding the last invoice number (default: grouped-invoice-number in the database)",
+    ),
+    ("invoice.due", "DAYS", "days until payment is due"),
+    ("invoice.notes", "TEXT", "closing note, e.g. payment details"),
+    (
+        "goal.<title>",
+        "DURATION[/PERIOD]",
+        "target for a group per day, week or month, e.g. 20h/week",
+    ),
+    (
+        "database",
+        "on|off|PATH",
+        "read timewarrior's database (default: $TIMEWARRIORDB or ~/.timewarrior) instead of stdin",
+    ),
+    (
+        "range",
+        "RANGE",
+        "with --database: day, yesterday, week, lastweek, month, lastmonth, all or START..END",
+    ),
+    (
+        "compare",
+        "PATH|database",
+        "compare with the previous period, from a timewarrior export or the database",
+    ),
+    (
+        "quality",
+        "on|off",
+        "list overlapping intervals and gaps in the working day",
+    ),
+    (
+        "quality.gap",
+        "DURATION",
+        "shortest gap to report (default: 30min)",
+    ),
+    (
+        "quality.hours",
+        "HH:MM-HH:MM",
+        "working hours to look for gaps in (default: 09:00-17:00)",
+    ),
+    (
+        "quality.days",
+        "DAY,...",
+        "working days, e.g. mon,tue,wed,thu,fri (the default)",
+    ),
+    (
+        "quality.fail",
+        "on|off",
+        "exit with code 2 when overlaps or gaps are found",
+    ),
+    (
+        "distribution",
+        "off|on|hours|weekdays",
+        "histograms of the time per hour of the day and per weekday (default: off)",
+    ),
+    (
+        "distribution.groups",
+        "on|off",
+        "also show the histograms for every top-level group",
+    ),
+    (
+        "sections",
+        "NAME,...",
+        "title, table, totals, goals, intervals, annotations (default: all)",
+    ),
+    ("format", "text|json|csv|tsv|markdown|org|html", "output format (default: text)"),
+    (
+        "export",
+        "groups|intervals",
+        "what csv and tsv export (default: groups)",
+    ),
+    (
+        "delimiter",
+        "CHAR",
+        "field delimiter for csv and tsv (default: , or tab)",
+    ),
+    (
+        "timezone",
+        "local|ZONE",
+        "IANA timezone for dates and times (default: local)",
+    ),
+    (
+        "lenient",
+        "on|off",
+        "skip intervals that cannot be parsed instead of failing",
+    ),
+    (
+        "running",
+        "include|exclude",
+        "count the running interval up to now, or leave it out (default: include)",
+    ),
+    (
+        "now",
+        "TIMESTAMP",
+        "the current time, e.g. 20231015T120000Z, for reproducible reports",
+    ),
+];
+
+/// What rows are sorted by, from `reports.grouped.sort`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Longest first.
+    Duration,
+    /// Alphabetically by title.
+    Title,
+}
+
+/// How intervals are grouped into rows, from `reports.grouped.grouping`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// One row per distinct, ordered tag list.
+    Combination,
+    /// One row per distinct tag set, whatever the order of the tags.
+    Unordered,
+    /// One row per individual tag; rows overlap.
+    Tag,
+    /// Tags split into levels, with subtotals at every level.
+    Tree,
+    /// One row per annotation key, ignoring tags.
+    Annotation,
+}
+
+/// What the CSV and TSV formats export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Export {
+    /// The grouped rows, as in the table.
+    Groups,
+    /// One record per interval.
+    Intervals,
+}
+
+/// Which side of the tags column titles are aligned to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// How titles wider than the space left for them are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Cut off with an ellipsis.
+    Truncate,
+    /// Continued on the following lines.
+    Wrap,
+}
+
+/// The output format, from `reports.grouped.format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The terminal table.
+    Text,
+    /// A document following [`crate::render::json::SCHEMA`].
+    Json,
+    Csv,
+    Tsv,
+    /// A Markdown table.
+    Markdown,
+    /// An Org mode table.
+    Org,
+    /// A standalone HTML page.
+    Html,
+}
+
+/// Which parts of the report are shown, from `reports.grouped.sections`.
+#[derive(Debug, Clone, Copy)]
+pub struct Sections {
+    pub title: bool,
+    pub table: bool,
+    pub totals: bool,
+    pub goals: bool,
+    pub intervals: bool,
+    pub annotations: bool,
+}
+
+impl Default for Sections {
+    fn default() -> Self {
+        Sections {
+            title: true,
+            table: true,
+            totals: true,
+            goals: true,
+            intervals: true,
+            annotations: true,
+        }
+    }
+}
+
+/// Where the previous period comes from when comparing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compare {
+    /// A file in the extension format, or a bare JSON array of intervals.
+    File(PathBuf),
+    /// Timewarrior's database.
+    Database,
+}
+
+/// Everything that shapes a report, read from the `reports.grouped.*`
+/// settings by [`Options::from_data`]. [`Options::default`] is the report
+/// without any settings.
+#[derive(Debug)]
+pub struct Options {
+    pub sort: SortKey,
+    /// Sorts shortest or reverse-alphabetically first.
+    pub reverse: bool,
+    pub grouping: Grouping,
+    /// Aliases and filters applied to the tags before grouping, with
+    /// [`Data::apply_tag_rules`].
+    pub tags: TagRules,
+    /// Category rules, in the order they are tried.
+    pub categories: Vec<CategoryRule>,
+    /// The annotation key intervals are grouped by as well as their tags.
+    pub annotation: Option<AnnotationKey>,
+    /// Lists which category rule every interval matched instead of the
+    /// report.
+    pub explain: bool,
+    /// Splits tags into tree levels.
+    pub tree_separator: String,
+    /// The deepest tree level shown, 0 for top-level only.
+    pub tree_depth: Option<usize>,
+    /// Set when the table has one column per period.
+    pub pivot: Option<Period>,
+    /// The minimum width of the tags column.
+    pub min_width: usize,
+    /// The width to fit tables to; `None` for unlimited.
+    pub width: Option<usize>,
+    pub align: Align,
+    pub overflow: Overflow,
+    /// Whether the text output is coloured.
+    pub color: bool,
+    pub theme: Theme,
+    /// Set when billing columns are shown.
+    pub billing: Option<Billing>,
+    /// Set when an invoice is printed instead of the report.
+    pub invoice: Option<InvoiceConfig>,
+    pub goals: Vec<Goal>,
+    /// Set when the data quality checks run.
+    pub quality: Option<QualityConfig>,
+    /// Set when the distribution histograms are shown.
+    pub distribution: Option<DistributionConfig>,
+    /// Where to read the previous period from.
+    pub compare: Option<Compare>,
+    pub sections: Sections,
+    pub format: Format,
+    /// What the CSV and TSV formats export.
+    pub export: Export,
+    /// Overrides the CSV or TSV delimiter.
+    pub delimiter: Option<char>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            sort: SortKey::Duration,
+            reverse: false,
+            grouping: Grouping::Combination,
+            tags: TagRules::default(),
+            categories: vec![],
+            annotation: None,
+            explain: false,
+            tree_separator: String::from(":"),
+            tree_depth: None,
+            pivot: None,
+            min_width: 12,
+            width: terminal_width(),
+            align: Align::Right,
+            overflow: Overflow::Truncate,
+            color: true,
+            theme: Theme::default(),
+            billing: None,
+            invoice: None,
+            goals: vec![],
+            compare: None,
+            quality: None,
+            distribution: None,
+            sections: Sections::default(),
+            format: Format::Text,
+            export: Export::Groups,
+            delimiter: None,
+        }
+    }
+}
+
+fn invalid(name: &str, value: &str, message: &str) -> Error {
+    Error::Setting {
+        name: format!("{}{}", SETTINGS_PREFIX, name),
+        value: value.into(),
+        message: message.into(),
+    }
+}
+
+/// The `reports.grouped.<prefix>.<name>` settings as `(name, value)` pairs,
+/// ordered by name.
+fn prefixed_settings<'a>(data: &'a Data, prefix: &str) -> Vec<(&'a str, &'a str)> {
+    let prefix = format!("{}{}.", SETTINGS_PREFIX, prefix);
+    let mut settings: Vec<(&str, &str)> = data
+        .settings
+        .iter()
+        .filter_map(|(key, value)| key.strip_prefix(&prefix).map(|name| (name, value.0.trim())))
+        .collect();
+    settings.sort_unstable();
+    settings
+}
+
+/// Parses durations such as `90`, `90min`, `1.5h` or `2d`. Plain numbers
+/// are minutes.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let minutes = match unit.trim() {
+        "" | "m" | "min" | "mins" | "minutes" => number,
+        "h" | "hour" | "hours" => number * 60.0,
+        "d" | "day" | "days" => number * 60.0 * 24.0,
+        _ => return None,
+    };
+    Some(Duration::seconds((minutes * 60.0).round() as i64))
+}
+
+fn parse_tag_rules(data: &Data) -> Result<TagRules, Error> {
+    let filters = |name: &str| match data.find_setting(&format!("{}{}", SETTINGS_PREFIX, name)) {
+        Some(value) => {
+            TagFilter::parse_list(&value.0).map_err(|message| invalid(name, &value.0, &message))
+        }
+        None => Ok(vec![]),
+    };
+    let mut rules = TagRules {
+        include: filters("include")?,
+        exclude: filters("exclude")?,
+        strip: filters("strip")?,
+        ..TagRules::default()
+    };
+    for (tag, alias) in prefixed_settings(data, "alias") {
+        if alias.is_empty() {
+            return Err(invalid(&format!("alias.{}", tag), alias, "expected a tag"));
+        }
+        rules.aliases.insert(tag.to_string(), alias.to_string());
+    }
+    Ok(rules)
+}
+
+fn parse_color(name: &str, value: &str) -> Result<Color, Error> {
+    value
+        .parse()
+        .map_err(|_| invalid(name, value, "unknown colour"))
+}
+
+fn parse_theme(data: &Data) -> Result<Theme, Error> {
+    let mut theme = Theme::default();
+    if let Some(value) = data.find_setting(&format!("{}theme", SETTINGS_PREFIX)) {
+        let value = value.0.trim();
+        theme.palette = Palette::named(value).ok_or_else(|| {
+            invalid(
+                "theme",
+                value,
+                "expected 'default', 'dark', 'light' or 'mono'",
+            )
+        })?;
+    }
+    for (role, value) in prefixed_settings(data, "theme") {
+        let name = format!("theme.{}", role);
+        let color = match value {
+            "" | "none" => None,
+            value => Some(parse_color(&name, value)?),
+        };
+        match role {
+            "good" => theme.palette.good = color,
+            "bad" => theme.palette.bad = color,
+            "warning" => theme.palette.warning = color,
+            "accent" => theme.palette.accent = color,
+            "muted" => theme.palette.muted = color,
+            _ => {
+                return Err(invalid(
+                    &name,
+                    value,
+                    "expected theme.good, theme.bad, theme.warning, theme.accent or theme.muted",
+                ))
+            }
+        }
+    }
+
+    for (title, value) in prefixed_settings(data, "highlight") {
+        if !matches!(value, "" | "none") {
+            let color = parse_color(&format!("highlight.{}", title), value)?;
+            theme.highlights.insert(title.to_string(), color);
+        }
+    }
+    for (pattern, value) in prefixed_settings(data, "color.tag") {
+        let color = parse_color(&format!("color.tag.{}", pattern), value)?;
+        theme
+            .rules
+            .push((RowMatcher::Tag(TagPattern::parse(pattern)), color));
+    }
+    for (pattern, value) in prefixed_settings(data, "color.regex") {
+        let name = format!("color.regex.{}", pattern);
+        let regex = Regex::new(pattern)
+            .map_err(|error| invalid(&name, pattern, &format!("invalid regex: {}", error)))?;
+        theme
+            .rules
+            .push((RowMatcher::Regex(regex), parse_color(&name, value)?));
+    }
+    for (percent, value) in prefixed_settings(data, "color.above") {
+        let name = format!("color.above.{}", percent);
+        let threshold = percent
+            .trim_end_matches('%')
+            .parse::<f64>()
+            .map_err(|_| invalid(&name, value, "expected a percentage in the name"))?;
+        theme
+            .thresholds
+            .push((threshold, parse_color(&name, value)?));
+    }
+    theme.thresholds.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    Ok(theme)
+}
+
+fn parse_billing(data: &Data) -> Result<Option<Billing>, Error> {
+    let setting = |name: &str| {
+        data.find_setting(&format!("{}{}", SETTINGS_PREFIX, name))
+            .map(|value| value.0.trim())
+    };
+    let rate = |name: &str, value: &str| {
+        value
+            .parse::<f64>()
+            .map_err(|_| invalid(name, value, "expected a number"))
+    };
+
+    let mut billing = Billing::default();
+    if let Some(value) = setting("rate") {
+        billing.default_rate = Some(rate("rate", value)?);
+    }
+    for (pattern, value) in prefixed_settings(data, "rate") {
+        billing.rates.push((
+            TagPattern::parse(pattern),
+            rate(&format!("rate.{}", pattern), value)?,
+        ));
+    }
+    let enabled = match data.find_setting(&format!("{}billing", SETTINGS_PREFIX)) {
+        Some(value) => value.value_to_bool(),
+        None => billing.default_rate.is_some() || !billing.rates.is_empty(),
+    };
+    if !enabled {
+        return Ok(None);
+    }
+
+    if let Some(value) = setting("billing.increment") {
+        billing.increment = Some(
+            parse_duration(value)
+                .ok_or_else(|| invalid("billing.increment", value, "expected a duration"))?,
+        );
+    }
+    if let Some(value) = setting("billing.rounding") {
+        billing.rounding = match value {
+            "interval" => Rounding::Interval,
+            "group" => Rounding::Group,
+            _ => {
+                return Err(invalid(
+                    "billing.rounding",
+                    value,
+                    "expected 'interval' or 'group'",
+                ))
+            }
+        };
+    }
+    if let Some(value) = setting("billing.currency") {
+        billing.currency = value.to_string();
+    }
+    if let Some(value) = setting("billing.exclude") {
+        billing.exclude = TagPattern::parse_list(value);
+    }
+    Ok(Some(billing))
+}
+
+fn parse_invoice(data: &Data) -> Result<Option<InvoiceConfig>, Error> {
+    let setting = |name: &str| {
+        data.find_setting(&format!("{}{}", SETTINGS_PREFIX, name))
+            .map(|value| value.0.trim())
+    };
+    let lines = |value: &str| -> Vec<String> {
+        value
+            .split(';')
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect()
+    };
+    match data.find_setting(&format!("{}invoice", SETTINGS_PREFIX)) {
+        Some(value) if value.value_to_bool() => {}
+        _ => return Ok(None),
+    }
+
+    let mut config = InvoiceConfig {
+        tax_label: String::from("Tax"),
+        ..InvoiceConfig::default()
+    };
+    if let Some(value) = setting("invoice.from") {
+        config.from = lines(value);
+    }
+    if let Some(value) = setting("invoice.client") {
+        config.client = lines(value);
+    }
+    if let Some(value) = setting("invoice.tax") {
+        config.tax_rate = Some(
+            value
+                .trim_end_matches('%')
+                .parse()
+                .map_err(|_| invalid("invoice.tax", value, "expected a percentage"))?,
+        );
+    }
+    if let Some(value) = setting("invoice.tax.label") {
+        config.tax_label = value.to_string();
+    }
+    if let Some(value) = setting("invoice.number").filter(|value| !value.is_empty()) {
+        config.number = Some(value.to_string());
+    }
+    if let Some(value) = setting("invoice.number.prefix") {
+        config.number_prefix = value.to_string();
+    }
+    if let Some(value) = setting("invoice.state").filter(|value| !value.is_empty()) {
+        config.state = Some(PathBuf::from(value));
+    }
+    if let Some(value) = setting("invoice.due") {
+        config.due_days = Some(
+            value
+                .parse()
+                .map_err(|_| invalid("invoice.due", value, "expected a number of days"))?,
+        );
+    }
+    if let Some(value) = setting("invoice.notes").filter(|value| !value.is_empty()) {
+        config.notes = Some(value.to_string());
+    }
+    Ok(Some(config))
+}
+
+fn parse_distribution(data: &Data) -> Result<Option<DistributionConfig>, Error> {
+    let (hours, weekdays) = match data
+        .find_setting(&format!("{}distribution", SETTINGS_PREFIX))
+        .map(|value| value.0.trim())
+    {
+        None | Some("" | "off") => return Ok(None),
+        Some("on") => (true, true),
+        Some("hours") => (true, false),
+        Some("weekdays") => (false, true),
+        Some(value) => {
+            return Err(invalid(
+                "distribution",
+                value,
+                "expected 'off', 'on', 'hours' or 'weekdays'",
+            ))
+        }
+    };
+    let groups = data
+        .find_setting(&format!("{}distribution.groups", SETTINGS_PREFIX))
+        .is_some_and(Value::value_to_bool);
+    Ok(Some(DistributionConfig {
+        hours,
+        weekdays,
+        groups,
+    }))
+}
+
+fn parse_quality(data: &Data) -> Result<Option<QualityConfig>, Error> {
+    let setting = |name: &str| {
+        data.find_setting(&format!("{}{}", SETTINGS_PREFIX, name))
+            .map(|value| value.0.trim())
+    };
+    match data.find_setting(&format!("{}quality", SETTINGS_PREFIX)) {
+        Some(value) if value.value_to_bool() => {}
+        _ => return Ok(None),
+    }
+
+    let mut config = QualityConfig::default();
+    if let Some(value) = setting("quality.gap") {
+        config.gap = parse_duration(value)
+            .ok_or_else(|| invalid("quality.gap", value, "expected a duration"))?;
+    }
+    if let Some(value) = setting("quality.hours") {
+        let time = |text: &str| NaiveTime::parse_from_str(text.trim(), "%H:%M").ok();
+        config.hours = value
+            .split_once('-')
+            .and_then(|(start, end)| Some((time(start)?, time(end)?)))
+            .filter(|(start, end)| start < end)
+            .ok_or_else(|| invalid("quality.hours", value, "expected HH:MM-HH:MM"))?;
+    }
+    if let Some(value) = setting("quality.days") {
+        config.days = value
+            .split(',')
+            .map(str::trim)
+            .filter(|day| !day.is_empty())
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .map_err(|_| invalid("quality.days", value, "unknown day"))
+            })
+            .collect::<Result<_, _>>()?;
+    }
+    if let Some(value) = data.find_setting(&format!("{}quality.fail", SETTINGS_PREFIX)) {
+        config.fail = value.value_to_bool();
+    }
+    Ok(Some(config))
+}
+
+impl Options {
+    /// Reads the options from `data`'s `reports.grouped.*` settings, after
+    /// any command-line overrides were merged into them. Settings that are
+    /// not set keep their [`Options::default`] value.
+    ///
+    /// Fails with [`Error::Setting`] for the first value that doesn't parse.
+    pub fn from_data(data: &Data) -> Result<Options, Error> {
+        let mut options = Options::default();
+        let setting = |name: &str| {
+            data.find_setting(&format!("{}{}", SETTINGS_PREFIX, name))
+                .map(|value| value.0.trim())
+        };
+
+        if let Some(value) = setting("sort") {
+            options.sort = match value {
+                "duration" => SortKey::Duration,
+                "title" => SortKey::Title,
+                _ => return Err(invalid("sort", value, "expected 'duration' or 'title'")),
+            };
+        }
+        if let Some(value) = data.find_setting(&format!("{}reverse", SETTINGS_PREFIX)) {
+            options.reverse = value.value_to_bool();
+        }
+        if let Some(value) = setting("grouping") {
+            options.grouping = match value {
+                "combination" => Grouping::Combination,
+                "unordered" => Grouping::Unordered,
+                "tag" => Grouping::Tag,
+                "tree" => Grouping::Tree,
+                "annotation" => Grouping::Annotation,
+                _ => {
+                    return Err(invalid(
+                        "grouping",
+                        value,
+                        "expected 'combination', 'unordered', 'tag', 'tree' or 'annotation'",
+                    ))
+                }
+            };
+        }
+        options.annotation = match setting("annotation") {
+            None | Some("" | "off") if options.grouping == Grouping::Annotation => {
+                Some(AnnotationKey::Text)
+            }
+            None | Some("" | "off") => None,
+            Some(value) => Some(
+                AnnotationKey::parse(value)
+                    .map_err(|message| invalid("annotation", value, &message))?,
+            ),
+        };
+        options.tags = parse_tag_rules(data)?;
+        for (number, value) in prefixed_settings(data, "category") {
+            let name = format!("category.{}", number);
+            let number = number
+                .parse()
+                .map_err(|_| invalid(&name, value, "expected a number after 'category.'"))?;
+            let rule = CategoryRule::parse(number, value)
+                .map_err(|message| invalid(&name, value, &message))?;
+            options.categories.push(rule);
+        }
+        options.categories.sort_by_key(|rule| rule.number);
+        if let Some(value) = data.find_setting(&format!("{}explain", SETTINGS_PREFIX)) {
+            options.explain = value.value_to_bool();
+        }
+        if let Some(value) = setting("tree.separator") {
+            if value.is_empty() {
+                return Err(invalid("tree.separator", value, "must not be empty"));
+            }
+            options.tree_separator = value.to_string();
+        }
+        if let Some(value) = setting("tree.depth") {
+            options.tree_depth = Some(
+                value
+                    .parse()
+                    .map_err(|_| invalid("tree.depth", value, "expected a number"))?,
+            );
+        }
+        if let Some(value) = setting("pivot") {
+            options.pivot = match value {
+                "off" | "" => None,
+                "day" => Some(Period::Day),
+                "week" => Some(Period::Week),
+                "month" => Some(Period::Month),
+                _ => {
+                    return Err(invalid(
+                        "pivot",
+                        value,
+                        "expected 'off', 'day', 'week' or 'month'",
+                    ))
+                }
+            };
+        }
+        if let Some(value) = setting("min_width") {
+            options.min_width = value
+                .parse()
+                .map_err(|_| invalid("min_width", value, "expected a number"))?;
+        }
+        if let Some(value) = setting("width") {
+            options.width = match value
+                .parse()
+                .map_err(|_| invalid("width", value, "expected a number"))?
+            {
+                0 => None,
+                width => Some(width),
+            };
+        }
+        options.align = match setting("align") {
+            None if options.grouping == Grouping::Tree => Align::Left,
+            None | Some("right") => Align::Right,
+            Some("left") => Align::Left,
+            Some(value) => return Err(invalid("align", value, "expected 'right' or 'left'")),
+        };
+        if let Some(value) = setting("overflow") {
+            options.overflow = match value {
+                "truncate" => Overflow::Truncate,
+                "wrap" => Overflow::Wrap,
+                _ => return Err(invalid("overflow", value, "expected 'truncate' or 'wrap'")),
+            };
+        }
+        options.color = match data.find_setting(&format!("{}color", SETTINGS_PREFIX)) {
+            Some(value) if value.0.trim() != "auto" => value.value_to_bool(),
+            _ => auto_color(data),
+        };
+        options.theme = parse_theme(data)?;
+
+        options.billing = parse_billing(data)?;
+        for (title, value) in prefixed_settings(data, "goal") {
+            options.goals.push(Goal::parse(title, value).ok_or_else(|| {
+                invalid(
+                    &format!("goal.{}", title),
+                    value,
+                    "expected a duration, optionally per day, week or month, e.g. 20h/week",
+                )
+            })?);
+        }
+
+        options.quality = parse_quality(data)?;
+        options.distribution = parse_distribution(data)?;
+        options.compare = match setting("compare") {
+            None | Some("" | "off") => None,
+            Some("database") => Some(Compare::Database),
+            Some(path) => Some(Compare::File(PathBuf::from(path))),
+        };
+
+        if let Some(value) = setting("sections") {
+            let mut sections = Sections {
+                title: false,
+                table: false,
+                totals: false,
+                goals: false,
+                intervals: false,
+                annotations: false,
+            };
+            for name in value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+            {
+                match name {
+                    "title" => sections.title = true,
+                    "table" => sections.table = true,
+                    "totals" => sections.totals = true,
+                    "goals" => sections.goals = true,
+                    "intervals" => sections.intervals = true,
+                    "annotations" => sections.annotations = true,
+                    _ => return Err(invalid("sections", value, "unknown section")),
+                }
+            }
+            options.sections = sections;
+        }
+        if let Some(value) = setting("format") {
+            options.format = match value {
+                "text" => Format::Text,
+                "json" => Format::Json,
+                "csv" => Format::Csv,
+                "tsv" => Format::Tsv,
+                "markdown" => Format::Markdown,
+                "org" => Format::Org,
+                "html" => Format::Html,
+                _ => return Err(invalid("format", value, "unknown format")),
+            };
+        }
+        if let Some(value) = setting("export") {
+            options.export = match value {
+                "groups" => Export::Groups,
+                "intervals" => Export::Intervals,
+                _ => return Err(invalid("export", value, "expected 'groups' or 'intervals'")),
+            };
+        }
+        if let Some(value) = data.find_setting(&format!("{}delimiter", SETTINGS_PREFIX)) {
+            let mut chars = value.0.chars();
+            options.delimiter = match (chars.next(), chars.next()) {
+                (Some(delimiter), None) => Some(delimiter),
+                _ if value.0 == "\\t" || value.0 == "tab" => Some('\t'),
+                _ => {
+                    return Err(invalid(
+                        "delimiter",
+                        &value.0,
+                        "expected a single character",
+                    ))
+                }
+            };
+        }
+
+        options.invoice = parse_invoice(data)?;
+        if options.invoice.is_some() {
+            if options.billing.is_none() {
+                return Err(invalid(
+                    "invoice",
+                    "on",
+                    "an invoice needs a rate, e.g. reports.grouped.rate",
+                ));
+            }
+            if !matches!(
+                options.format,
+                Format::Text | Format::Markdown | Format::Html
+            ) {
+                return Err(invalid(
+                    "format",
+                    setting("format").unwrap_or_default(),
+                    "invoices are written as 'text', 'markdown' or 'html'",
+                ));
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn is_known_setting(name: &str) -> bool {
+    SETTINGS
+        .iter()
+        .any(|(setting, _, _)| match setting.find('<') {
+            Some(index) => name.starts_with(&setting[..index]) && name.len() > index,
+            None => name == *setting,
+        })
+}
+
+/// Turns `--name=value`, `--name` and `--no-name` arguments into
+/// `reports.grouped.<name>` settings, with `on` and `off` as the values of
+/// the bare forms. Dashes in the name become underscores, so `--min-width`
+/// sets `reports.grouped.min_width`.
+///
+/// Fails with [`Error::Argument`] for anything that isn't a known option.
+pub fn parse_args(args: &[String]) -> Result<Vec<(String, String)>, Error> {
+    args.iter()
+        .map(|arg| {
+            let flag = arg
+                .strip_prefix("--")
+                .ok_or_else(|| Error::Argument(format!("unexpected argument '{}'", arg)))?;
+            let (flag, value) = match flag.split_once('=') {
+                Some((name, value)) => (name, value),
+                None => match flag.strip_prefix("no-") {
+                    Some(flag) => (flag, "off"),
+                    None => (flag, "on"),
+                },
+            };
+            let name = match flag.split_once('.') {
+                Some((name, rest)) => format!("{}.{}", name.replace('-', "_"), rest),
+                None => flag.replace('-', "_"),
+            };
+            if !is_known_setting(&name) {
+                return Err(Error::Argument(format!("unknown option '--{}'", flag)));
+            }
+            Ok((format!("{}{}", SETTINGS_PREFIX, name), value.to_string()))
+        })
+        .collect()
+}
+
+/// The `--help` text: how options are given, followed by every option with
+/// its values and description.
+pub fn usage() -> String {
+    let mut usage = String::from(
+        "Usage: timewarrior-grouped [--<option>=<value>]...\n\n\
+         Reads the timewarrior extension protocol from stdin. Every option can also\n\
+         be set in timewarrior.cfg as reports.grouped.<option>, with dashes written\n\
+         as underscores (--min-width becomes reports.grouped.min_width).\n\nOptions:\n",
+    );
+    SETTINGS.iter().for_each(|(name, value, description)| {
+        usage.push_str(&format!(
+            "  --{:<30} {}\n",
+            format!("{}={}", name.replace('_', "-"), value),
+            description
+        ));
+    });
+    usage
+}
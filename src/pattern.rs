@@ -0,0 +1,104 @@
+//! Tag patterns used by configuration settings.
+
+/// Matches a tag either exactly or, when written with a trailing `*`, by
+/// prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagPattern {
+    Exact(String),
+    Prefix(String),
+}
+
+impl TagPattern {
+    /// Parses `tag` or `prefix*`.
+    pub fn parse(pattern: &str) -> TagPattern {
+        match pattern.strip_suffix('*') {
+            Some(prefix) => TagPattern::Prefix(prefix.to_string()),
+            None => TagPattern::Exact(pattern.to_string()),
+        }
+    }
+
+    /// Parses a comma-separated list of patterns, ignoring empty entries.
+    pub fn parse_list(patterns: &str) -> Vec<TagPattern> {
+        patterns
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(TagPattern::parse)
+            .collect()
+    }
+
+    pub fn matches(&self, tag: &str) -> bool {
+        match self {
+            TagPattern::Exact(exact) => tag == exact,
+            TagPattern::Prefix(prefix) => tag.starts_with(prefix.as_str()),
+        }
+    }
+
+    /// How specific the pattern is: exact matches beat any prefix and
+    /// longer prefixes beat shorter ones.
+    pub fn specificity(&self) -> usize {
+        match self {
+            TagPattern::Exact(_) => usize::MAX,
+            TagPattern::Prefix(prefix) => prefix.len(),
+        }
+    }
+}
+
+/// Matches a tag exactly, by glob such as `client:*` or by a regular
+/// expression written as `/regex/`.
+#[derive(Debug, Clone)]
+pub enum TagFilter {
+    Exact(String),
+    Glob(glob::Pattern),
+    Regex(regex::Regex),
+}
+
+impl TagFilter {
+    /// Parses `tag`, a glob containing `*`, `?` or `[`, or `/regex/`.
+    pub fn parse(filter: &str) -> Result<TagFilter, String> {
+        if let Some(regex) = filter
+            .strip_prefix('/')
+            .and_then(|filter| filter.strip_suffix('/'))
+        {
+            return regex::Regex::new(regex)
+                .map(TagFilter::Regex)
+                .map_err(|error| format!("invalid regex: {}", error));
+        }
+        match filter.contains(['*', '?', '[']) {
+            true => glob::Pattern::new(filter)
+                .map(TagFilter::Glob)
+                .map_err(|error| format!("invalid glob: {}", error)),
+            false => Ok(TagFilter::Exact(filter.to_string())),
+        }
+    }
+
+    /// Parses a comma-separated list of filters, ignoring empty entries.
+    /// Commas inside `/regex/` entries don't split the list.
+    pub fn parse_list(filters: &str) -> Result<Vec<TagFilter>, String> {
+        let mut entries = vec![];
+        let mut rest = filters.trim_start();
+        while !rest.is_empty() {
+            let end = match rest.strip_prefix('/').and_then(|regex| regex.find('/')) {
+                Some(index) => rest[index + 2..]
+                    .find(',')
+                    .map_or(rest.len(), |comma| index + 2 + comma),
+                None => rest.find(',').unwrap_or(rest.len()),
+            };
+            entries.push(rest[..end].trim());
+            rest = rest[end..].trim_start_matches(',').trim_start();
+        }
+        entries
+            .into_iter()
+            .filter(|entry| !entry.is_empty())
+            .map(TagFilter::parse)
+            .collect()
+    }
+
+    pub fn matches(&self, tag: &str) -> bool {
+        match self {
+            TagFilter::Exact(exact) => tag == exact,
+            TagFilter::Glob(pattern) => pattern.matches(tag),
+            TagFilter::Regex(regex) => regex.is_match(tag),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Time-bucketed reports: tag groups as rows and calendar periods as
+//! columns.
+
+use crate::group::interval_groups;
+use crate::input::Data;
+use crate::options::{Options, SortKey};
+use chrono::{Datelike, Duration, NaiveDate};
+
+/// The calendar period a pivot column covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    /// An ISO week, Monday to Sunday.
+    Week,
+    Month,
+}
+
+impl Period {
+    /// The first day of the period `date` falls in.
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Month => date.with_day(1).unwrap(),
+        }
+    }
+
+    /// The first day of the period after the one starting at `start`.
+    pub fn next(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => start + Duration::days(1),
+            Period::Week => start + Duration::days(7),
+            Period::Month => match start.month() {
+                12 => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1).unwrap(),
+                month => NaiveDate::from_ymd_opt(start.year(), month + 1, 1).unwrap(),
+            },
+        }
+    }
+
+    /// The column heading for the period starting at `start`, e.g.
+    /// `Sun 10-15`, `2023-W41` or `2023-10`.
+    pub fn label(&self, start: NaiveDate) -> String {
+        match self {
+            Period::Day => start.format("%a %m-%d").to_string(),
+            Period::Week => format!(
+                "{}-W{:02}",
+                start.iso_week().year(),
+                start.iso_week().week()
+            ),
+            Period::Month => start.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// One tag group's time per period.
+#[derive(Debug)]
+pub struct PivotRow {
+    pub title: String,
+    /// The tags the title was built from, as in
+    /// [`crate::group::GroupReportRow::tags`].
+    pub tags: Vec<String>,
+    /// The annotation key, as in
+    /// [`crate::group::GroupReportRow::annotation`].
+    pub annotation: Option<String>,
+    /// One duration per entry of [`Pivot::periods`].
+    pub cells: Vec<Duration>,
+    pub total: Duration,
+}
+
+/// Tag groups by calendar periods, with row and column totals.
+#[derive(Debug)]
+pub struct Pivot {
+    pub period: Period,
+    /// The first day of every period in the report range, in order.
+    pub periods: Vec<NaiveDate>,
+    pub rows: Vec<PivotRow>,
+    /// The time of all intervals per period. With overlapping groupings
+    /// this is less than the sum of the column's cells.
+    pub totals: Vec<Duration>,
+    pub total: Duration,
+}
+
+impl Pivot {
+    /// A row's share of the total, in percent, like
+    /// [`crate::group::Report::percent`].
+    pub fn percent(&self, duration: &Duration) -> f64 {
+        match self.total.num_seconds() {
+            0 => 0.0,
+            total => duration.num_seconds() as f64 / total as f64 * 100.0,
+        }
+    }
+
+    /// Orders the rows by their totals or titles like
+    /// [`crate::group::sort_rows`].
+    pub fn sort(&mut self, sort: SortKey, reverse: bool) {
+        match sort {
+            SortKey::Duration => self.rows.sort_by_key(|row| std::cmp::Reverse(row.total)),
+            SortKey::Title => self.rows.sort_by(|a, b| a.title.cmp(&b.title)),
+        }
+        if reverse {
+            self.rows.reverse();
+        }
+    }
+}
+
+impl Data {
+    /// Splits every interval's clipped duration at local period boundaries
+    /// in the report's timezone and sums the pieces per group and period.
+    /// Rows are grouped as in `options.grouping` and keep the order in which
+    /// their group first appears.
+    pub fn pivot(&self, period: Period, options: &Options) -> Pivot {
+        let range = self.report_range();
+        let mut pieces = vec![];
+        self.intervals.iter().for_each(|interval| {
+            let Some((mut start, end)) = interval.clipped_bounds(&range) else {
+                return;
+            };
+            let groups = interval_groups(interval, options);
+            while start < end {
+                let period_start = period.start_of(start.date_naive());
+                let next = self
+                    .timezone
+                    .from_local(&period.next(period_start).and_hms_opt(0, 0, 0).unwrap());
+                let piece_end = next.min(end);
+                pieces.push((groups.clone(), period_start, piece_end - start));
+                start = piece_end;
+            }
+        });
+
+        let first = range
+            .start
+            .map(|start| start.date_naive())
+            .or_else(|| pieces.iter().map(|(_, date, _)| *date).min());
+        let last = range
+            .end
+            .map(|end| (end - Duration::seconds(1)).date_naive())
+            .or_else(|| pieces.iter().map(|(_, date, _)| *date).max());
+        let mut periods = vec![];
+        if let (Some(first), Some(last)) = (first, last) {
+            let mut date = period.start_of(first);
+            while date <= last {
+                periods.push(date);
+                date = period.next(date);
+            }
+        }
+
+        let mut rows: Vec<PivotRow> = vec![];
+        let mut totals = vec![Duration::zero(); periods.len()];
+        pieces.into_iter().for_each(|(groups, date, duration)| {
+            let Some(column) = periods.iter().position(|period| *period == date) else {
+                return;
+            };
+            totals[column] = totals[column] + duration;
+            groups.into_iter().for_each(|group| {
+                let index = match rows
+                    .iter()
+                    .position(|row| row.tags == group.tags && row.annotation == group.annotation)
+                {
+                    Some(index) => index,
+                    None => {
+                        rows.push(PivotRow {
+                            title: group.title(),
+                            tags: group.tags,
+                            annotation: group.annotation,
+                            cells: vec![Duration::zero(); periods.len()],
+                            total: Duration::zero(),
+                        });
+                        rows.len() - 1
+                    }
+                };
+                rows[index].cells[column] = rows[index].cells[column] + duration;
+                rows[index].total = rows[index].total + duration;
+            });
+        });
+        let total = totals
+            .iter()
+            .fold(Duration::zero(), |sum, cell| sum + *cell);
+
+        Pivot {
+            period,
+            periods,
+            rows,
+            totals,
+            total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::test_data;
+    use crate::options::Options;
+
+    #[test]
+    fn splits_time_by_local_day_and_group() {
+        let data = test_data(
+            &[
+                ("temp.report.start", "20231014T000000Z"),
+                ("annotation", "text"),
+                ("timezone", "Europe/Berlin"),
+            ],
+            &[
+                "1014T2130 - 1014T2300 # docs",
+                "0800 - 0900 # # 42",
+                "0900 - 0930 # 42",
+            ],
+        );
+        let options = Options::from_data(&data).unwrap();
+        let pivot = data.pivot(Period::Day, &options);
+        assert_eq!(pivot.periods.len(), 3);
+        let rows: Vec<(&str, Vec<i64>)> = pivot
+            .rows
+            .iter()
+            .map(|row| {
+                let minutes = row.cells.iter().map(|cell| cell.num_minutes()).collect();
+                (row.title.as_str(), minutes)
+            })
+            .collect();
+        // 21:30 UTC is 23:30 in Berlin, so half an hour falls on the 14th.
+        assert_eq!(
+            rows,
+            [
+                ("docs", vec![30, 60, 0]),
+                ("/ 42", vec![0, 60, 0]),
+                ("42", vec![0, 30, 0]),
+            ]
+        );
+        let totals: Vec<i64> = pivot
+            .totals
+            .iter()
+            .map(|total| total.num_minutes())
+            .collect();
+        assert_eq!(totals, [30, 150, 0]);
+        assert_eq!(pivot.percent(&pivot.rows[0].total), 50.0);
+    }
+}
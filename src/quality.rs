@@ -0,0 +1,232 @@
+//! Data quality checks: overlapping intervals, which count time twice, and
+//! untracked gaps within working hours.
+
+use crate::input::{Data, Interval};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveTime, Weekday};
+
+/// Settings for the checks, from `reports.grouped.quality.*`.
+#[derive(Debug, Clone)]
+pub struct QualityConfig {
+    /// Gaps shorter than this are not reported.
+    pub gap: Duration,
+    /// The working day, in the report's timezone.
+    pub hours: (NaiveTime, NaiveTime),
+    pub days: Vec<Weekday>,
+    /// Whether problems make the report exit with a non-zero code.
+    pub fail: bool,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        QualityConfig {
+            gap: Duration::minutes(30),
+            hours: (
+                NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            ),
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            fail: false,
+        }
+    }
+}
+
+/// Two intervals that cover the same time.
+#[derive(Debug, Clone)]
+pub struct Overlap {
+    /// The tags of both intervals, the earlier-starting one first.
+    pub tags: (Vec<String>, Vec<String>),
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+}
+
+/// Untracked time within working hours.
+#[derive(Debug, Clone)]
+pub struct Gap {
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+}
+
+/// The problems found in the report range, in chronological order.
+#[derive(Debug, Default)]
+pub struct Quality {
+    pub overlaps: Vec<Overlap>,
+    pub gaps: Vec<Gap>,
+}
+
+impl Quality {
+    pub fn is_clean(&self) -> bool {
+        self.overlaps.is_empty() && self.gaps.is_empty()
+    }
+}
+
+impl Data {
+    /// Finds overlapping intervals and gaps of at least `config.gap` within
+    /// the working hours of every working day in the report range. Both are
+    /// clipped to the range; time after now is never a gap. Without a
+    /// report range, the days the intervals span are checked. Intervals the
+    /// tag rules dropped, in [`Data::filtered`], are checked too: filtering
+    /// changes what is reported, not what was tracked.
+    pub fn quality(&self, config: &QualityConfig) -> Quality {
+        let range = self.report_range();
+        let mut bounds: Vec<(&Interval, DateTime<FixedOffset>, DateTime<FixedOffset>)> = self
+            .intervals
+            .iter()
+            .chain(&self.filtered)
+            .filter_map(|interval| {
+                interval
+                    .clipped_bounds(&range)
+                    .map(|(start, end)| (interval, start, end))
+            })
+            .collect();
+        bounds.sort_by_key(|(_, start, _)| *start);
+
+        let mut overlaps = vec![];
+        for (position, (first, _, first_end)) in bounds.iter().enumerate() {
+            for (second, second_start, second_end) in &bounds[position + 1..] {
+                if second_start >= first_end {
+                    break;
+                }
+                overlaps.push(Overlap {
+                    tags: (first.tags.clone(), second.tags.clone()),
+                    start: *second_start,
+                    end: (*first_end).min(*second_end),
+                });
+            }
+        }
+        overlaps.sort_by_key(|overlap| overlap.start);
+
+        let mut gaps = vec![];
+        let now = self.now;
+        let first = range
+            .start
+            .or_else(|| bounds.first().map(|(_, start, _)| *start));
+        let last = range
+            .end
+            .or_else(|| bounds.iter().map(|(_, _, end)| *end).max());
+        if let (Some(first), Some(last)) = (first, last) {
+            let mut date = first.date_naive();
+            while date <= last.date_naive() {
+                if config.days.contains(&date.weekday()) {
+                    let mut start = self
+                        .timezone
+                        .from_local(&date.and_time(config.hours.0))
+                        .max(first);
+                    let end = self
+                        .timezone
+                        .from_local(&date.and_time(config.hours.1))
+                        .min(last)
+                        .min(now);
+                    // Walk the sorted intervals, moving the start of the
+                    // untracked time past every interval that covers it.
+                    for (_, interval_start, interval_end) in &bounds {
+                        if *interval_end <= start {
+                            continue;
+                        }
+                        if *interval_start >= end {
+                            break;
+                        }
+                        if *interval_start > start && *interval_start - start >= config.gap {
+                            gaps.push(Gap {
+                                start,
+                                end: *interval_start,
+                            });
+                        }
+                        start = start.max(*interval_end);
+                    }
+                    if end > start && end - start >= config.gap {
+                        gaps.push(Gap { start, end });
+                    }
+                }
+                date = date.succ_opt().unwrap();
+            }
+        }
+
+        Quality { overlaps, gaps }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::test_data;
+    use crate::options::Options;
+
+    /// A Monday, checked from 09:00 to 17:00 with `settings`.
+    fn monday(settings: &[(&str, &str)], intervals: &[&str]) -> (Data, Options) {
+        let settings: Vec<(&str, &str)> = [
+            ("temp.report.start", "20231016T000000Z"),
+            ("temp.report.end", "20231017T000000Z"),
+            ("now", "20231017T000000Z"),
+            ("quality", "on"),
+        ]
+        .into_iter()
+        .chain(settings.iter().copied())
+        .collect();
+        let data = test_data(&settings, intervals);
+        let options = Options::from_data(&data).unwrap();
+        (data, options)
+    }
+
+    fn minutes(start: &DateTime<FixedOffset>, end: &DateTime<FixedOffset>) -> (String, i64) {
+        (
+            start.format("%H:%M").to_string(),
+            (*end - *start).num_minutes(),
+        )
+    }
+
+    #[test]
+    fn finds_overlaps_and_gaps_within_working_hours() {
+        let (data, options) = monday(
+            &[("quality.gap", "20min")],
+            &[
+                "1016T0800 - 1016T1000 # code",
+                "1016T0945 - 1016T1200 # review",
+                "1016T1215 - 1016T1600 # code",
+            ],
+        );
+        let quality = data.quality(options.quality.as_ref().unwrap());
+        let overlaps: Vec<_> = quality
+            .overlaps
+            .iter()
+            .map(|overlap| (minutes(&overlap.start, &overlap.end), overlap.tags.clone()))
+            .collect();
+        assert_eq!(
+            overlaps,
+            [(
+                (String::from("09:45"), 15),
+                (vec![String::from("code")], vec![String::from("review")])
+            )]
+        );
+        // The 15 minutes at noon are shorter than the minimum gap.
+        let gaps: Vec<_> = quality
+            .gaps
+            .iter()
+            .map(|gap| minutes(&gap.start, &gap.end))
+            .collect();
+        assert_eq!(gaps, [(String::from("16:00"), 60)]);
+    }
+
+    #[test]
+    fn checks_intervals_the_tag_rules_dropped() {
+        let (mut data, options) = monday(
+            &[("exclude", "42")],
+            &[
+                "1016T0900 - 1016T0930 # 42",
+                "1016T0930 - 1016T1700 # code",
+                "1016T1000 - 1016T1030 # 42 review",
+            ],
+        );
+        data.apply_tag_rules(&options.tags);
+        assert_eq!(data.intervals.len(), 1);
+        let quality = data.quality(options.quality.as_ref().unwrap());
+        assert!(quality.gaps.is_empty());
+        assert_eq!(quality.overlaps.len(), 1);
+        assert_eq!(quality.overlaps[0].tags.1, ["42", "review"]);
+    }
+}
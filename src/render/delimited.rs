@@ -0,0 +1,171 @@
+//! CSV and TSV export, either of the grouped rows or of the intervals.
+
+use crate::input::Data;
+use crate::options::{Export, Format, Options};
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// Quotes `value` if it contains the delimiter, a quote or a line break,
+/// doubling any quotes in it (RFC 4180).
+pub fn quote(value: &str, delimiter: char) -> Cow<'_, str> {
+    if value.contains([delimiter, '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+fn write_record(out: &mut impl Write, delimiter: char, fields: &[&str]) -> io::Result<()> {
+    let record: Vec<Cow<str>> = fields.iter().map(|field| quote(field, delimiter)).collect();
+    writeln!(out, "{}", record.join(&delimiter.to_string()))
+}
+
+fn hours(duration: &chrono::Duration) -> String {
+    format!("{:.2}", duration.num_seconds() as f64 / 3600.0)
+}
+
+/// Writes `options.export` as delimiter-separated values with a header
+/// record. The delimiter defaults to a comma for CSV and a tab for TSV.
+pub fn render(data: &Data, options: &Options, out: &mut impl Write) -> io::Result<()> {
+    let delimiter = options.delimiter.unwrap_or(match options.format {
+        Format::Tsv => '\t',
+        _ => ',',
+    });
+    match options.export {
+        Export::Groups => render_groups(data, options, delimiter, out),
+        Export::Intervals => render_intervals(data, delimiter, out),
+    }
+}
+
+fn render_groups(
+    data: &Data,
+    options: &Options,
+    delimiter: char,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let report = data.report(options);
+    let bill = options
+        .billing
+        .as_ref()
+        .map(|billing| billing.bill(data, &report, options));
+    let mut header = vec!["title", "depth", "seconds", "minutes", "hours", "percent"];
+    if bill.is_some() {
+        header.extend(["billable_hours", "rate", "amount", "excluded"]);
+    }
+    write_record(out, delimiter, &header)?;
+    for (index, line) in report.lines.iter().enumerate() {
+        let duration = &line.row.duration;
+        let mut fields = vec![
+            line.row.title.clone(),
+            line.depth.to_string(),
+            duration.num_seconds().to_string(),
+            duration.num_minutes().to_string(),
+            hours(duration),
+            format!("{:.2}", report.percent(duration)),
+        ];
+        if let Some(bill) = &bill {
+            let billed = &bill.lines[index];
+            fields.extend([
+                hours(&billed.billable),
+                billed.rate.map(|rate| rate.to_string()).unwrap_or_default(),
+                format!("{:.2}", billed.amount),
+                billed.excluded.to_string(),
+            ]);
+        }
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        write_record(out, delimiter, &fields)?;
+    }
+    if options.sections.totals {
+        let mut fields = vec![
+            "TOTAL".to_string(),
+            String::new(),
+            report.total.num_seconds().to_string(),
+            report.total.num_minutes().to_string(),
+            hours(&report.total),
+            String::new(),
+        ];
+        if let Some(bill) = &bill {
+            fields.extend([
+                hours(&bill.billable),
+                String::new(),
+                format!("{:.2}", bill.amount),
+                String::new(),
+            ]);
+        }
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        write_record(out, delimiter, &fields)?;
+    }
+    Ok(())
+}
+
+/// One record per interval with its recorded start and end (empty while
+/// running) in the report's timezone, and its duration clipped to the
+/// report range so the durations add up to the report's total.
+fn render_intervals(data: &Data, delimiter: char, out: &mut impl Write) -> io::Result<()> {
+    let range = data.report_range();
+    write_record(
+        out,
+        delimiter,
+        &["start", "end", "seconds", "hours", "tags", "annotation"],
+    )?;
+    for interval in &data.intervals {
+        let duration = interval.clipped_duration(&range);
+        write_record(
+            out,
+            delimiter,
+            &[
+                &interval.start.to_rfc3339(),
+                &interval.end.map(|end| end.to_rfc3339()).unwrap_or_default(),
+                &duration.num_seconds().to_string(),
+                &hours(&duration),
+                &interval.title(),
+                interval.annotation.as_deref().unwrap_or(""),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::test_data;
+
+    fn export(settings: &[(&str, &str)], intervals: &[&str]) -> String {
+        let data = test_data(settings, intervals);
+        let options = Options::from_data(&data).unwrap();
+        let mut out = Vec::new();
+        render(&data, &options, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn quotes_fields_with_delimiters_quotes_or_line_breaks() {
+        assert_eq!(quote("code", ','), "code");
+        assert_eq!(quote("code, docs", ','), "\"code, docs\"");
+        assert_eq!(quote("code, docs", '\t'), "code, docs");
+        assert_eq!(quote("say \"hi\"", ','), "\"say \"\"hi\"\"\"");
+        assert_eq!(quote("two\nlines", ';'), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn exports_intervals_with_quoted_fields() {
+        let csv = export(
+            &[("format", "csv"), ("export", "intervals")],
+            &[r#"0800 - 0930 # "say \"hi\"" code # fix, again"#],
+        );
+        assert_eq!(
+            csv,
+            "start,end,seconds,hours,tags,annotation\n\
+             2023-10-15T08:00:00+00:00,2023-10-15T09:30:00+00:00,5400,1.50,\
+             \"say \"\"hi\"\", code\",\"fix, again\"\n"
+        );
+        let tsv = export(&[("format", "tsv")], &["0800 - 0930 # code docs"]);
+        assert_eq!(
+            tsv,
+            "title\tdepth\tseconds\tminutes\thours\tpercent\n\
+             code, docs\t0\t5400\t90\t1.50\t100.00\n\
+             TOTAL\t\t5400\t90\t1.50\t\n"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! The `explain` listing: which category rule every interval matched.
+
+use super::{display_width, pad_string_end};
+use crate::group::{annotation_key, grouping_tags, interval_groups, GroupKey};
+use crate::input::{Data, Interval};
+use crate::options::{Grouping, Options};
+use colored::Colorize;
+use std::io::{self, Write};
+
+/// The interval's tags as a title, or `(untagged)`.
+fn tags_title(interval: &Interval) -> String {
+    match interval.title() {
+        title if title.is_empty() => String::from("(untagged)"),
+        title => title,
+    }
+}
+
+/// Writes one line per interval in the report range: its local start and
+/// end, its tags, the groups it counts under and the rule that put it
+/// there. Intervals no rule matches fall back to their joined tags.
+pub fn render(data: &Data, options: &Options, out: &mut impl Write) -> io::Result<()> {
+    let range = data.report_range();
+    let intervals: Vec<(&Interval, (_, _))> = data
+        .intervals
+        .iter()
+        .filter_map(|interval| Some((interval, interval.clipped_bounds(&range)?)))
+        .collect();
+    let tags_width = intervals
+        .iter()
+        .map(|(interval, _)| display_width(&tags_title(interval)))
+        .chain(["TAGS".len()])
+        .max()
+        .unwrap_or(0);
+    let groups: Vec<String> = intervals
+        .iter()
+        .map(|(interval, _)| {
+            let groups: Vec<String> = match options.grouping {
+                Grouping::Tree => vec![GroupKey {
+                    tags: grouping_tags(interval, options),
+                    annotation: annotation_key(interval, options),
+                }
+                .title()],
+                _ => interval_groups(interval, options)
+                    .iter()
+                    .map(|group| group.title())
+                    .collect(),
+            };
+            match groups.join("; ") {
+                group if group.is_empty() => String::from("(untagged)"),
+                group => group,
+            }
+        })
+        .collect();
+    let group_width = groups
+        .iter()
+        .map(|group| display_width(group))
+        .chain(["GROUP".len()])
+        .max()
+        .unwrap_or(0);
+
+    let header = format!(
+        "{:<35} {}    {} RULE",
+        "INTERVAL",
+        pad_string_end("TAGS", tags_width),
+        pad_string_end("GROUP", group_width)
+    );
+    writeln!(out, "{}", header.bold().underline())?;
+    for ((interval, (start, end)), group) in intervals.iter().zip(&groups) {
+        let span = format!(
+            "{} - {}",
+            start.format("%Y-%m-%d %H:%M"),
+            end.format("%Y-%m-%d %H:%M")
+        );
+        let rule = match interval.category(&options.categories) {
+            Some(rule) => format!("category.{}: {}", rule.number, rule.source),
+            None => String::from("no rule matched"),
+        };
+        writeln!(
+            out,
+            "{:<35} {} -> {} {}",
+            span,
+            pad_string_end(&tags_title(interval), tags_width),
+            pad_string_end(group, group_width),
+            options.theme.muted(&rule)
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,278 @@
+//! A self-contained HTML page with the grouped table, a donut chart of the
+//! split, a per-day stacked bar chart and the annotations. Everything is
+//! inline, so the file can be mailed or opened offline.
+
+use crate::input::Data;
+use crate::options::Options;
+use crate::pivot::{Period, PivotRow};
+use std::f64::consts::PI;
+use std::io::{self, Write};
+
+const PALETTE: &[&str] = &[
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac",
+];
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2em;color:#222}\
+h1{font-size:1.4em}h2{font-size:1.1em;margin-top:2em}\
+table{border-collapse:collapse}th,td{padding:.25em .75em;border-bottom:1px solid #ddd}\
+th{text-align:left}td.n{text-align:right;font-variant-numeric:tabular-nums}\
+tr.total td{font-weight:bold;border-top:2px solid #222}\
+.swatch{display:inline-block;width:.8em;height:.8em;margin-right:.4em;border-radius:2px}\
+.charts{display:flex;flex-wrap:wrap;gap:3em;align-items:flex-end}\
+.note{color:#666;font-size:.9em}";
+
+/// Escapes text for use in HTML content and attribute values.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn color(index: usize) -> &'static str {
+    PALETTE[index % PALETTE.len()]
+}
+
+fn hours(duration: &chrono::Duration) -> f64 {
+    duration.num_seconds() as f64 / 3600.0
+}
+
+/// Writes the report as an HTML document.
+pub fn render(data: &Data, options: &Options, out: &mut impl Write) -> io::Result<()> {
+    let report = data.report(options);
+    let title = match data.report_title() {
+        title if title.is_empty() => String::from("Time report"),
+        title => format!("Time report {}", title),
+    };
+    // Top-level groups get a colour each; they make up the charts.
+    let groups: Vec<(&str, chrono::Duration)> = report
+        .lines
+        .iter()
+        .filter(|line| line.depth == 0)
+        .map(|line| (line.row.title.as_str(), line.row.duration))
+        .collect();
+    let group_color = |title: &str| {
+        groups
+            .iter()
+            .position(|(group, _)| *group == title)
+            .map(color)
+    };
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"en\">")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(&title))?;
+    writeln!(out, "<style>{}</style>", STYLE)?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    if options.sections.title {
+        writeln!(out, "<h1>{}</h1>", escape(&title))?;
+    }
+
+    if options.sections.table {
+        writeln!(out, "<table>")?;
+        writeln!(
+            out,
+            "<tr><th>Tags</th><th>Minutes</th><th>Hours</th><th>%</th></tr>"
+        )?;
+        for line in &report.lines {
+            let swatch = match (line.depth, group_color(&line.row.title)) {
+                (0, Some(color)) => format!(
+                    "<span class=\"swatch\" style=\"background:{}\"></span>",
+                    color
+                ),
+                _ => String::new(),
+            };
+            writeln!(
+                out,
+                "<tr><td style=\"padding-left:{}em\">{}{}{}</td><td class=\"n\">{}</td>\
+                 <td class=\"n\">{:.1}</td><td class=\"n\">{:.0}</td></tr>",
+                0.75 + 1.5 * line.depth as f64,
+                swatch,
+                escape(&line.label),
+                match line.running {
+                    true => " <span title=\"running\">&#9654;</span>",
+                    false => "",
+                },
+                line.row.duration.num_minutes(),
+                hours(&line.row.duration),
+                report.percent(&line.row.duration),
+            )?;
+        }
+        if options.sections.totals {
+            writeln!(
+                out,
+                "<tr class=\"total\"><td>Total</td><td class=\"n\">{}</td>\
+                 <td class=\"n\">{:.1}</td><td></td></tr>",
+                report.total.num_minutes(),
+                hours(&report.total),
+            )?;
+        }
+        writeln!(out, "</table>")?;
+        if report.overlapping {
+            writeln!(
+                out,
+                "<p class=\"note\">Percentages overlap: intervals with several tags \
+                 count under each of them.</p>"
+            )?;
+        }
+    }
+
+    writeln!(out, "<div class=\"charts\">")?;
+    render_donut(&groups, out)?;
+    render_days(data, options, &groups, out)?;
+    writeln!(out, "</div>")?;
+
+    let range = data.report_range();
+    let annotated_intervals: Vec<_> = data
+        .intervals
+        .iter()
+        .filter(|interval| interval.annotation.is_some())
+        .collect();
+    if options.sections.annotations && !annotated_intervals.is_empty() {
+        writeln!(out, "<h2>Annotations</h2>")?;
+        writeln!(out, "<ul>")?;
+        for interval in annotated_intervals {
+            writeln!(
+                out,
+                "<li>{} <strong>{}</strong> ({} min): {}</li>",
+                interval.start.format("%Y-%m-%d %H:%M"),
+                escape(&interval.title()),
+                interval.clipped_duration(&range).num_minutes(),
+                escape(interval.annotation.as_deref().unwrap_or("")),
+            )?;
+        }
+        writeln!(out, "</ul>")?;
+    }
+
+    if options.sections.intervals {
+        writeln!(
+            out,
+            "<p class=\"note\">{} intervals</p>",
+            data.intervals.len()
+        )?;
+    }
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+/// A donut of the top-level groups' shares. With overlapping groups the
+/// shares are of their sum, so the ring stays whole.
+fn render_donut(groups: &[(&str, chrono::Duration)], out: &mut impl Write) -> io::Result<()> {
+    let sum: f64 = groups.iter().map(|(_, duration)| hours(duration)).sum();
+    if sum <= 0.0 {
+        return Ok(());
+    }
+    let (size, radius, width) = (220.0, 80.0, 36.0);
+    let center = size / 2.0;
+    let circumference = 2.0 * PI * radius;
+    writeln!(
+        out,
+        "<figure><svg width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\" role=\"img\">",
+        size
+    )?;
+    let mut offset = 0.0;
+    for (index, (title, duration)) in groups.iter().enumerate() {
+        let length = hours(duration) / sum * circumference;
+        writeln!(
+            out,
+            "<circle cx=\"{c}\" cy=\"{c}\" r=\"{r}\" fill=\"none\" stroke=\"{color}\" \
+             stroke-width=\"{w}\" stroke-dasharray=\"{length:.3} {rest:.3}\" \
+             stroke-dashoffset=\"{offset:.3}\" transform=\"rotate(-90 {c} {c})\">\
+             <title>{title}: {percent:.0}%</title></circle>",
+            c = center,
+            r = radius,
+            w = width,
+            color = color(index),
+            length = length,
+            rest = circumference - length,
+            offset = -offset,
+            title = escape(title),
+            percent = hours(duration) / sum * 100.0,
+        )?;
+        offset += length;
+    }
+    writeln!(out, "</svg><figcaption>Split</figcaption></figure>")
+}
+
+/// One stacked bar per day of the range, split by top-level group.
+fn render_days(
+    data: &Data,
+    options: &Options,
+    groups: &[(&str, chrono::Duration)],
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let pivot = data.pivot(Period::Day, options);
+    let rows: Vec<(usize, &PivotRow)> = pivot
+        .rows
+        .iter()
+        .filter_map(|row| {
+            groups
+                .iter()
+                .position(|(title, _)| *title == row.title)
+                .map(|index| (index, row))
+        })
+        .collect();
+    let day_hours: Vec<f64> = (0..pivot.periods.len())
+        .map(|column| rows.iter().map(|(_, row)| hours(&row.cells[column])).sum())
+        .collect();
+    let max = day_hours.iter().cloned().fold(0.0, f64::max);
+    if pivot.periods.is_empty() || max <= 0.0 {
+        return Ok(());
+    }
+
+    let (bar, gap, height, label) = (28.0, 8.0, 180.0, 34.0);
+    let width = pivot.periods.len() as f64 * (bar + gap) + gap;
+    writeln!(
+        out,
+        "<figure><svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" role=\"img\" \
+         font-size=\"10\" text-anchor=\"middle\">",
+        w = width,
+        h = height + label,
+    )?;
+    for (column, period) in pivot.periods.iter().enumerate() {
+        let x = gap + column as f64 * (bar + gap);
+        let mut y = height;
+        for (index, row) in &rows {
+            let value = hours(&row.cells[column]);
+            if value <= 0.0 {
+                continue;
+            }
+            let bar_height = value / max * (height - 12.0);
+            y -= bar_height;
+            writeln!(
+                out,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{}\" height=\"{:.1}\" fill=\"{}\">\
+                 <title>{}: {:.1} h</title></rect>",
+                x,
+                y,
+                bar,
+                bar_height,
+                color(*index),
+                escape(&row.title),
+                value,
+            )?;
+        }
+        if day_hours[column] > 0.0 {
+            writeln!(
+                out,
+                "<text x=\"{:.1}\" y=\"{:.1}\">{:.1}</text>",
+                x + bar / 2.0,
+                y - 3.0,
+                day_hours[column],
+            )?;
+        }
+        writeln!(
+            out,
+            "<text x=\"{0:.1}\" y=\"{1:.1}\">{2}</text><text x=\"{0:.1}\" y=\"{3:.1}\">{4}</text>",
+            x + bar / 2.0,
+            height + 14.0,
+            period.format("%a"),
+            height + 27.0,
+            period.format("%m-%d"),
+        )?;
+    }
+    writeln!(out, "</svg><figcaption>Hours per day</figcaption></figure>")
+}
@@ -0,0 +1,266 @@
+//! Invoice documents as plain text, Markdown or HTML.
+
+use super::html::escape;
+use super::{display_width, pad_string, pad_string_end};
+use crate::invoice::Invoice;
+use crate::options::Format;
+use std::io::{self, Write};
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2em;color:#222;max-width:50em}\
+h1{font-size:1.4em}.parties{display:flex;gap:4em;margin:1.5em 0}\
+.parties h2{font-size:.8em;text-transform:uppercase;color:#666;margin:0}\
+table{border-collapse:collapse;width:100%}th,td{padding:.25em .75em;border-bottom:1px solid #ddd}\
+th{text-align:left}td.n,th.n{text-align:right;font-variant-numeric:tabular-nums}\
+tr.total td{font-weight:bold;border-top:2px solid #222}.note{color:#666}";
+
+fn money(amount: f64) -> String {
+    format!("{:.2}", amount)
+}
+
+/// `amount` followed by the currency, if there is one.
+fn money_with_currency(amount: f64, currency: &str) -> String {
+    match currency {
+        "" => money(amount),
+        currency => format!("{} {}", money(amount), currency),
+    }
+}
+
+fn tax_title(invoice: &Invoice) -> String {
+    match invoice.tax_rate {
+        Some(rate) => format!("{} ({}%)", invoice.tax_label, rate),
+        None => invoice.tax_label.clone(),
+    }
+}
+
+/// The label and value of each detail line: number, dates and period.
+fn details(invoice: &Invoice) -> Vec<(&'static str, String)> {
+    let mut details = vec![
+        ("Invoice", invoice.number.clone()),
+        ("Date", invoice.date.format("%Y-%m-%d").to_string()),
+    ];
+    if let Some(due) = invoice.due {
+        details.push(("Due", due.format("%Y-%m-%d").to_string()));
+    }
+    if !invoice.period.is_empty() {
+        details.push(("Period", invoice.period.clone()));
+    }
+    details
+}
+
+/// The totals below the line items; tax only when a tax rate is set.
+fn totals(invoice: &Invoice) -> Vec<(String, f64)> {
+    let mut totals = vec![(String::from("Subtotal"), invoice.subtotal)];
+    if invoice.tax_rate.is_some() {
+        totals.push((tax_title(invoice), invoice.tax));
+    }
+    totals.push((String::from("Total"), invoice.total));
+    totals
+}
+
+/// Writes `invoice` in `format`: HTML, Markdown or, for any other format,
+/// plain text.
+pub fn render(invoice: &Invoice, format: Format, out: &mut impl Write) -> io::Result<()> {
+    match format {
+        Format::Html => render_html(invoice, out),
+        Format::Markdown => render_markdown(invoice, out),
+        _ => render_text(invoice, out),
+    }
+}
+
+fn render_text(invoice: &Invoice, out: &mut impl Write) -> io::Result<()> {
+    for (label, value) in details(invoice) {
+        writeln!(
+            out,
+            "{} {}",
+            pad_string_end(&format!("{}:", label), 8),
+            value
+        )?;
+    }
+    for (heading, lines) in [("From", &invoice.from), ("To", &invoice.client)] {
+        if lines.is_empty() {
+            continue;
+        }
+        writeln!(out)?;
+        writeln!(out, "{}:", heading)?;
+        for line in lines {
+            writeln!(out, "  {}", line)?;
+        }
+    }
+    writeln!(out)?;
+
+    let amount_header = match invoice.currency.as_str() {
+        "" => String::from("AMOUNT"),
+        currency => format!("AMOUNT {}", currency),
+    };
+    let amount_width = invoice
+        .items
+        .iter()
+        .map(|item| money(item.amount).len())
+        .chain([money(invoice.total).len(), amount_header.len(), 10])
+        .max()
+        .unwrap_or(0);
+    let description_width = invoice
+        .items
+        .iter()
+        .map(|item| display_width(&item.description))
+        .chain(
+            totals(invoice)
+                .iter()
+                .map(|(label, _)| display_width(label)),
+        )
+        .chain(["DESCRIPTION".len()])
+        .max()
+        .unwrap_or(0);
+    let width = description_width + 2 * 11 + amount_width;
+    writeln!(
+        out,
+        "{} {:>10} {:>10} {:>width$}",
+        pad_string_end("DESCRIPTION", description_width),
+        "HOURS",
+        "RATE",
+        amount_header,
+        width = amount_width
+    )?;
+    writeln!(out, "{}", "-".repeat(width))?;
+    for item in &invoice.items {
+        writeln!(
+            out,
+            "{} {:10.2} {:>10} {:>width$}",
+            pad_string_end(&item.description, description_width),
+            item.hours,
+            money(item.rate),
+            money(item.amount),
+            width = amount_width
+        )?;
+    }
+    writeln!(out, "{}", "-".repeat(width))?;
+    for (label, amount) in totals(invoice) {
+        writeln!(
+            out,
+            "{}{}",
+            pad_string_end(&label, description_width + 22),
+            pad_string(&money(amount), amount_width + 1)
+        )?;
+    }
+
+    if let Some(notes) = &invoice.notes {
+        writeln!(out)?;
+        writeln!(out, "{}", notes)?;
+    }
+    Ok(())
+}
+
+fn render_markdown(invoice: &Invoice, out: &mut impl Write) -> io::Result<()> {
+    let escape = |cell: &str| cell.replace('|', "\\|");
+    writeln!(out, "# Invoice {}", invoice.number)?;
+    writeln!(out)?;
+    for (label, value) in details(invoice).iter().skip(1) {
+        writeln!(out, "- **{}:** {}", label, value)?;
+    }
+    for (heading, lines) in [("From", &invoice.from), ("To", &invoice.client)] {
+        if lines.is_empty() {
+            continue;
+        }
+        writeln!(out)?;
+        writeln!(out, "**{}:**  ", heading)?;
+        writeln!(out, "{}", lines.join("  \n"))?;
+    }
+    writeln!(out)?;
+
+    writeln!(out, "| Description | Hours | Rate | Amount |")?;
+    writeln!(out, "|:---|---:|---:|---:|")?;
+    for item in &invoice.items {
+        writeln!(
+            out,
+            "| {} | {:.2} | {} | {} |",
+            escape(&item.description),
+            item.hours,
+            money(item.rate),
+            money(item.amount)
+        )?;
+    }
+    for (label, amount) in totals(invoice) {
+        let amount = match label.as_str() {
+            "Total" => money_with_currency(amount, &invoice.currency),
+            _ => money(amount),
+        };
+        writeln!(out, "| **{}** | | | **{}** |", escape(&label), amount)?;
+    }
+
+    if let Some(notes) = &invoice.notes {
+        writeln!(out)?;
+        writeln!(out, "{}", notes)?;
+    }
+    Ok(())
+}
+
+fn render_html(invoice: &Invoice, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"en\">")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>Invoice {}</title>", escape(&invoice.number))?;
+    writeln!(out, "<style>{}</style>", STYLE)?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>Invoice {}</h1>", escape(&invoice.number))?;
+    writeln!(out, "<table class=\"details\">")?;
+    for (label, value) in details(invoice).iter().skip(1) {
+        writeln!(out, "<tr><th>{}</th><td>{}</td></tr>", label, escape(value))?;
+    }
+    writeln!(out, "</table>")?;
+
+    writeln!(out, "<div class=\"parties\">")?;
+    for (heading, lines) in [("From", &invoice.from), ("To", &invoice.client)] {
+        if lines.is_empty() {
+            continue;
+        }
+        let lines: Vec<String> = lines.iter().map(|line| escape(line)).collect();
+        writeln!(
+            out,
+            "<div><h2>{}</h2><p>{}</p></div>",
+            heading,
+            lines.join("<br>")
+        )?;
+    }
+    writeln!(out, "</div>")?;
+
+    writeln!(out, "<table>")?;
+    writeln!(
+        out,
+        "<tr><th>Description</th><th class=\"n\">Hours</th><th class=\"n\">Rate</th><th class=\"n\">Amount</th></tr>"
+    )?;
+    for item in &invoice.items {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td class=\"n\">{:.2}</td><td class=\"n\">{}</td><td class=\"n\">{}</td></tr>",
+            escape(&item.description),
+            item.hours,
+            money(item.rate),
+            money(item.amount)
+        )?;
+    }
+    for (label, amount) in totals(invoice) {
+        let (class, amount) = match label.as_str() {
+            "Total" => (
+                " class=\"total\"",
+                money_with_currency(amount, &invoice.currency),
+            ),
+            _ => ("", money(amount)),
+        };
+        writeln!(
+            out,
+            "<tr{}><td colspan=\"3\">{}</td><td class=\"n\">{}</td></tr>",
+            class,
+            escape(&label),
+            escape(&amount)
+        )?;
+    }
+    writeln!(out, "</table>")?;
+
+    if let Some(notes) = &invoice.notes {
+        writeln!(out, "<p class=\"note\">{}</p>", escape(notes))?;
+    }
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
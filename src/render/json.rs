@@ -0,0 +1,411 @@
+//! Machine-readable JSON output.
+//!
+//! The document carries `"schema": "timewarrior-grouped/report"` and a
+//! `version`. Fields are only ever added within a version; renaming or
+//! removing one bumps it.
+
+use crate::compare::Change;
+use crate::distribution::{Distribution, DistributionConfig, WEEKDAYS};
+use crate::input::Data;
+use crate::options::{Grouping, Options};
+use serde::Serialize;
+use std::io::{self, Write};
+
+pub const SCHEMA: &str = "timewarrior-grouped/report";
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Document<'a> {
+    schema: &'static str,
+    version: u32,
+    range: Range,
+    grouping: &'static str,
+    groups: Vec<Group<'a>>,
+    total: Amount,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_range: Option<Range>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_total: Option<ChangeJson>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    billing: Option<BillingTotal<'a>>,
+    overlapping: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    goals: Vec<GoalJson<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quality: Option<QualityJson<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distribution: Option<DistributionJson>,
+    now: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    running: Option<Running<'a>>,
+    interval_count: usize,
+    annotations: Vec<Annotation<'a>>,
+}
+
+#[derive(Serialize)]
+struct Range {
+    title: String,
+    start: Option<String>,
+    end: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Group<'a> {
+    title: &'a str,
+    tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    annotation_key: Option<&'a str>,
+    depth: usize,
+    #[serde(flatten)]
+    amount: Amount,
+    percent: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    billing: Option<Billed>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous: Option<ChangeJson>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    running: bool,
+}
+
+#[derive(Serialize)]
+struct Running<'a> {
+    start: String,
+    tags: &'a [String],
+    annotation: Option<&'a str>,
+    #[serde(flatten)]
+    amount: Amount,
+}
+
+#[derive(Serialize)]
+struct ChangeJson {
+    seconds: i64,
+    hours: f64,
+    delta_seconds: i64,
+    delta_hours: f64,
+    delta_percent: Option<f64>,
+}
+
+impl From<&Change> for ChangeJson {
+    fn from(change: &Change) -> Self {
+        ChangeJson {
+            seconds: change.previous.num_seconds(),
+            hours: round(change.previous.num_seconds() as f64 / 3600.0),
+            delta_seconds: change.delta.num_seconds(),
+            delta_hours: round(change.delta.num_seconds() as f64 / 3600.0),
+            delta_percent: change.percent.map(round),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Billed {
+    billable_hours: f64,
+    rate: Option<f64>,
+    amount: f64,
+    excluded: bool,
+}
+
+#[derive(Serialize)]
+struct BillingTotal<'a> {
+    currency: &'a str,
+    billable_hours: f64,
+    amount: f64,
+}
+
+#[derive(Serialize)]
+struct GoalJson<'a> {
+    title: &'a str,
+    target_hours: f64,
+    actual_hours: f64,
+    remaining_hours: f64,
+    percent: f64,
+    met: bool,
+}
+
+#[derive(Serialize)]
+struct DistributionJson {
+    #[serde(flatten)]
+    buckets: Buckets,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<GroupDistribution>,
+}
+
+#[derive(Serialize)]
+struct GroupDistribution {
+    title: String,
+    #[serde(flatten)]
+    buckets: Buckets,
+}
+
+/// One entry per hour and weekday, each with its share of the total.
+#[derive(Serialize)]
+struct Buckets {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hours: Vec<HourBucket>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    weekdays: Vec<WeekdayBucket>,
+}
+
+#[derive(Serialize)]
+struct HourBucket {
+    hour: u32,
+    #[serde(flatten)]
+    amount: Amount,
+    percent: f64,
+}
+
+#[derive(Serialize)]
+struct WeekdayBucket {
+    weekday: &'static str,
+    #[serde(flatten)]
+    amount: Amount,
+    percent: f64,
+}
+
+impl Buckets {
+    fn new(distribution: &Distribution, config: &DistributionConfig) -> Buckets {
+        let total = distribution.total().num_seconds();
+        let percent = |duration: &chrono::Duration| match total {
+            0 => 0.0,
+            total => round(duration.num_seconds() as f64 / total as f64 * 100.0),
+        };
+        Buckets {
+            hours: match config.hours {
+                true => (0..)
+                    .zip(&distribution.hours)
+                    .map(|(hour, duration)| HourBucket {
+                        hour,
+                        amount: (*duration).into(),
+                        percent: percent(duration),
+                    })
+                    .collect(),
+                false => vec![],
+            },
+            weekdays: match config.weekdays {
+                true => WEEKDAYS
+                    .into_iter()
+                    .zip(&distribution.weekdays)
+                    .map(|(weekday, duration)| WeekdayBucket {
+                        weekday,
+                        amount: (*duration).into(),
+                        percent: percent(duration),
+                    })
+                    .collect(),
+                false => vec![],
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct QualityJson<'a> {
+    overlaps: Vec<OverlapJson<'a>>,
+    gaps: Vec<GapJson>,
+}
+
+#[derive(Serialize)]
+struct OverlapJson<'a> {
+    start: String,
+    end: String,
+    minutes: i64,
+    tags: [&'a [String]; 2],
+}
+
+#[derive(Serialize)]
+struct GapJson {
+    start: String,
+    end: String,
+    minutes: i64,
+}
+
+#[derive(Serialize)]
+struct Amount {
+    seconds: i64,
+    minutes: i64,
+    hours: f64,
+}
+
+impl From<chrono::Duration> for Amount {
+    fn from(duration: chrono::Duration) -> Self {
+        Amount {
+            seconds: duration.num_seconds(),
+            minutes: duration.num_minutes(),
+            hours: round(duration.num_seconds() as f64 / 3600.0),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Annotation<'a> {
+    start: String,
+    end: Option<String>,
+    tags: &'a [String],
+    annotation: &'a str,
+    #[serde(flatten)]
+    amount: Amount,
+    percent: f64,
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn grouping_name(grouping: Grouping) -> &'static str {
+    match grouping {
+        Grouping::Combination => "combination",
+        Grouping::Unordered => "unordered",
+        Grouping::Tag => "tag",
+        Grouping::Tree => "tree",
+        Grouping::Annotation => "annotation",
+    }
+}
+
+/// Writes the report as a pretty-printed JSON document.
+pub fn render(data: &Data, options: &Options, out: &mut impl Write) -> io::Result<()> {
+    let report = data.report(options);
+    let bill = options
+        .billing
+        .as_ref()
+        .map(|billing| billing.bill(data, &report, options));
+    let range = data.report_range();
+    let goals = match options.sections.goals {
+        true => data.goal_progress(&options.goals, &report),
+        false => vec![],
+    };
+    let comparison = data.compare(&report, options);
+    let quality = options.quality.as_ref().map(|config| data.quality(config));
+    let hours = |duration: chrono::Duration| round(duration.num_seconds() as f64 / 3600.0);
+    let document = Document {
+        schema: SCHEMA,
+        version: SCHEMA_VERSION,
+        range: Range {
+            title: data.report_title(),
+            start: range.start.map(|start| start.to_rfc3339()),
+            end: range.end.map(|end| end.to_rfc3339()),
+        },
+        grouping: grouping_name(options.grouping),
+        groups: report
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| Group {
+                title: &line.row.title,
+                tags: &line.row.tags,
+                annotation_key: line.row.annotation.as_deref(),
+                depth: line.depth,
+                amount: line.row.duration.into(),
+                percent: round(report.percent(&line.row.duration)),
+                running: line.running,
+                billing: bill.as_ref().map(|bill| {
+                    let billed = &bill.lines[index];
+                    Billed {
+                        billable_hours: round(billed.billable.num_seconds() as f64 / 3600.0),
+                        rate: billed.rate,
+                        amount: round(billed.amount),
+                        excluded: billed.excluded,
+                    }
+                }),
+                previous: comparison
+                    .as_ref()
+                    .map(|comparison| (&comparison.lines[index]).into()),
+            })
+            .collect(),
+        total: report.total.into(),
+        previous_range: comparison.as_ref().map(|comparison| Range {
+            title: data
+                .previous
+                .as_deref()
+                .map(Data::report_title)
+                .unwrap_or_default(),
+            start: comparison.range.start.map(|start| start.to_rfc3339()),
+            end: comparison.range.end.map(|end| end.to_rfc3339()),
+        }),
+        previous_total: comparison
+            .as_ref()
+            .map(|comparison| (&comparison.total).into()),
+        billing: options
+            .billing
+            .as_ref()
+            .zip(bill.as_ref())
+            .map(|(billing, bill)| BillingTotal {
+                currency: &billing.currency,
+                billable_hours: round(bill.billable.num_seconds() as f64 / 3600.0),
+                amount: round(bill.amount),
+            }),
+        overlapping: report.overlapping,
+        goals: goals
+            .iter()
+            .map(|progress| GoalJson {
+                title: &progress.title,
+                target_hours: hours(progress.target),
+                actual_hours: hours(progress.actual),
+                remaining_hours: hours(progress.remaining()),
+                percent: round(progress.fraction() * 100.0),
+                met: progress.is_met(),
+            })
+            .collect(),
+        quality: quality.as_ref().map(|quality| QualityJson {
+            overlaps: quality
+                .overlaps
+                .iter()
+                .map(|overlap| OverlapJson {
+                    start: overlap.start.to_rfc3339(),
+                    end: overlap.end.to_rfc3339(),
+                    minutes: (overlap.end - overlap.start).num_minutes(),
+                    tags: [&overlap.tags.0, &overlap.tags.1],
+                })
+                .collect(),
+            gaps: quality
+                .gaps
+                .iter()
+                .map(|gap| GapJson {
+                    start: gap.start.to_rfc3339(),
+                    end: gap.end.to_rfc3339(),
+                    minutes: (gap.end - gap.start).num_minutes(),
+                })
+                .collect(),
+        }),
+        distribution: options
+            .distribution
+            .as_ref()
+            .map(|config| DistributionJson {
+                buckets: Buckets::new(&data.distribution(|_| true), config),
+                groups: match config.groups {
+                    true => data
+                        .group_distributions(&report, options)
+                        .into_iter()
+                        .map(|(title, distribution)| GroupDistribution {
+                            title,
+                            buckets: Buckets::new(&distribution, config),
+                        })
+                        .collect(),
+                    false => vec![],
+                },
+            }),
+        now: data.now.to_rfc3339(),
+        running: data.running_interval().map(|interval| Running {
+            start: interval.start.to_rfc3339(),
+            tags: &interval.tags,
+            annotation: interval.annotation.as_deref(),
+            amount: interval.clipped_duration(&range).into(),
+        }),
+        interval_count: data.intervals.len(),
+        annotations: data
+            .intervals
+            .iter()
+            .filter_map(|interval| {
+                let duration = interval.clipped_duration(&range);
+                interval.annotation.as_ref().map(|annotation| Annotation {
+                    start: interval.start.to_rfc3339(),
+                    end: interval.end.map(|end| end.to_rfc3339()),
+                    tags: &interval.tags,
+                    annotation,
+                    amount: duration.into(),
+                    percent: round(report.percent(&duration)),
+                })
+            })
+            .collect(),
+    };
+    serde_json::to_writer_pretty(&mut *out, &document)?;
+    writeln!(out)
+}
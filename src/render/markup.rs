@@ -0,0 +1,170 @@
+//! Markdown (GitHub flavoured) and Org-mode tables.
+
+use crate::group::Report;
+use crate::input::{Data, Interval};
+use crate::options::{Format, Options};
+use std::io::{self, Write};
+
+/// The differences between the two table dialects.
+struct Dialect {
+    heading: &'static str,
+    subheading: &'static str,
+    /// Whether the header is separated by an alignment row (`|---:|`)
+    /// rather than an Org `|---+---|` rule.
+    markdown: bool,
+}
+
+const MARKDOWN: Dialect = Dialect {
+    heading: "##",
+    subheading: "###",
+    markdown: true,
+};
+
+const ORG: Dialect = Dialect {
+    heading: "*",
+    subheading: "**",
+    markdown: false,
+};
+
+impl Dialect {
+    fn escape(&self, cell: &str) -> String {
+        match self.markdown {
+            true => cell.replace('|', "\\|"),
+            false => cell.replace('|', "\\vert{}"),
+        }
+    }
+
+    fn row(&self, cells: &[String]) -> String {
+        let cells: Vec<String> = cells.iter().map(|cell| self.escape(cell)).collect();
+        format!("| {} |", cells.join(" | "))
+    }
+
+    /// The rule below the header; every column but the first is numeric
+    /// unless `text_last` is set.
+    fn rule(&self, columns: usize, text_last: bool) -> String {
+        match self.markdown {
+            true => {
+                let mut cells = vec![":---".to_string()];
+                cells.extend(
+                    (1..columns).map(|column| match text_last && column + 1 == columns {
+                        true => ":---".to_string(),
+                        false => "---:".to_string(),
+                    }),
+                );
+                format!("|{}|", cells.join("|"))
+            }
+            false => format!("|{}|", vec!["---"; columns].join("+")),
+        }
+    }
+
+    fn strong(&self, text: &str) -> String {
+        match self.markdown {
+            true => format!("**{}**", text),
+            false => format!("*{}*", text),
+        }
+    }
+}
+
+fn amounts(duration: &chrono::Duration, report: &Report) -> [String; 3] {
+    [
+        duration.num_minutes().to_string(),
+        format!("{:.1}", duration.num_seconds() as f64 / 3600.0),
+        format!("{:.0}", report.percent(duration)),
+    ]
+}
+
+/// Writes the report as a Markdown or Org-mode document: the range as a
+/// heading, the grouped table with a total row and, if there are any, a
+/// table of annotated intervals.
+pub fn render(data: &Data, options: &Options, out: &mut impl Write) -> io::Result<()> {
+    let dialect = match options.format {
+        Format::Org => ORG,
+        _ => MARKDOWN,
+    };
+    let report = data.report(options);
+    let title = data.report_title();
+
+    if options.sections.title && !title.is_empty() {
+        writeln!(out, "{} {}", dialect.heading, title)?;
+        writeln!(out)?;
+    }
+
+    if options.sections.table {
+        writeln!(
+            out,
+            "{}",
+            dialect.row(&["Tags", "Minutes", "Hours", "%"].map(String::from))
+        )?;
+        writeln!(out, "{}", dialect.rule(4, false))?;
+        for line in &report.lines {
+            let [minutes, hours, percent] = amounts(&line.row.duration, &report);
+            let title = match line.running {
+                true => format!("{} ▶", line.row.title),
+                false => line.row.title.clone(),
+            };
+            writeln!(out, "{}", dialect.row(&[title, minutes, hours, percent]))?;
+        }
+        if options.sections.totals {
+            if !dialect.markdown {
+                writeln!(out, "{}", dialect.rule(4, false))?;
+            }
+            let [minutes, hours, _] = amounts(&report.total, &report);
+            writeln!(
+                out,
+                "{}",
+                dialect.row(&[
+                    dialect.strong("Total"),
+                    dialect.strong(&minutes),
+                    dialect.strong(&hours),
+                    String::new(),
+                ])
+            )?;
+        }
+        if report.overlapping {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "Percentages overlap: intervals with several tags count under each of them."
+            )?;
+        }
+    }
+
+    if options.sections.intervals {
+        writeln!(out)?;
+        writeln!(out, "Intervals: {}", data.intervals.len())?;
+    }
+
+    let annotated_intervals: Vec<&Interval> = data
+        .intervals
+        .iter()
+        .filter(|interval| interval.annotation.is_some())
+        .collect();
+    if options.sections.annotations && !annotated_intervals.is_empty() {
+        let range = data.report_range();
+        writeln!(out)?;
+        writeln!(out, "{} Annotations", dialect.subheading)?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{}",
+            dialect.row(&["Tags", "Minutes", "Hours", "%", "Annotation"].map(String::from))
+        )?;
+        writeln!(out, "{}", dialect.rule(5, true))?;
+        for interval in annotated_intervals {
+            let [minutes, hours, percent] = amounts(&interval.clipped_duration(&range), &report);
+            writeln!(
+                out,
+                "{}",
+                dialect.row(&[
+                    interval.title(),
+                    minutes,
+                    hours,
+                    percent,
+                    interval.annotation.clone().unwrap_or_default(),
+                ])
+            )?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+//! Renderers that turn [`Data`] into a report.
+
+use crate::input::Data;
+use crate::options::{Format, Options};
+use std::io::{self, Write};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+pub mod delimited;
+pub mod explain;
+pub mod html;
+pub mod invoice;
+pub mod json;
+pub mod markup;
+pub mod text;
+
+/// Writes the report in the format chosen in `options`.
+pub fn render(data: &Data, options: &Options, out: &mut impl Write) -> io::Result<()> {
+    match options.format {
+        Format::Text => text::render(data, options, out),
+        Format::Json => json::render(data, options, out),
+        Format::Csv | Format::Tsv => delimited::render(data, options, out),
+        Format::Markdown | Format::Org => markup::render(data, options, out),
+        Format::Html => html::render(data, options, out),
+    }
+}
+
+/// The width of the terminal stdout is connected to, or else stderr or the
+/// controlling terminal, as when timewarrior pipes the report through a
+/// pager, falling back to the `COLUMNS` environment variable. `None` when
+/// none of them is known.
+pub fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size()
+        .or_else(other_terminal_size)
+        .map(|(terminal_size::Width(width), _)| width as usize)
+        .or_else(|| std::env::var("COLUMNS").ok()?.trim().parse().ok())
+}
+
+#[cfg(unix)]
+fn other_terminal_size() -> Option<(terminal_size::Width, terminal_size::Height)> {
+    use std::os::unix::io::AsRawFd;
+    terminal_size::terminal_size_using_fd(std::io::stderr().as_raw_fd()).or_else(|| {
+        let tty = std::fs::File::open("/dev/tty").ok()?;
+        terminal_size::terminal_size_using_fd(tty.as_raw_fd())
+    })
+}
+
+#[cfg(not(unix))]
+fn other_terminal_size() -> Option<(terminal_size::Width, terminal_size::Height)> {
+    None
+}
+
+/// The number of terminal columns `s` takes up: wide characters such as CJK
+/// count twice, combining marks not at all.
+pub fn display_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// Right-aligns `s` to `len` columns. Longer strings are returned as is.
+pub fn pad_string(s: &str, len: usize) -> String {
+    match len.checked_sub(display_width(s)) {
+        Some(padding) => {
+            let mut padded_string = String::with_capacity(len);
+            for _ in 0..padding {
+                padded_string.push(' ');
+            }
+            padded_string.push_str(s);
+            padded_string
+        }
+        None => s.to_string(),
+    }
+}
+
+/// Left-aligns `s` to `len` columns. Longer strings are returned as is.
+pub fn pad_string_end(s: &str, len: usize) -> String {
+    let padding = len.saturating_sub(display_width(s));
+    format!("{}{}", s, " ".repeat(padding))
+}
+
+/// Shortens `s` to at most `width` columns, ending it with an ellipsis if
+/// anything was cut off.
+pub fn truncate(s: &str, width: usize) -> String {
+    if display_width(s) <= width {
+        return s.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let char_width = UnicodeWidthChar::width(c).unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += char_width;
+    }
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+/// Breaks `s` into lines of at most `width` columns, after spaces where
+/// possible and inside words that are too long on their own.
+pub fn wrap(s: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut used = 0;
+    for word in s.split_inclusive(' ') {
+        let word_width = display_width(word.trim_end());
+        if used > 0 && used + word_width > width {
+            lines.push(line.trim_end().to_string());
+            line.clear();
+            used = 0;
+        }
+        for c in word.chars() {
+            let char_width = UnicodeWidthChar::width(c).unwrap_or(0);
+            if used + char_width > width && c != ' ' {
+                lines.push(line.trim_end().to_string());
+                line.clear();
+                used = 0;
+            }
+            line.push(c);
+            used += char_width;
+        }
+    }
+    if !line.trim_end().is_empty() || lines.is_empty() {
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::read_data;
+
+    const INPUT: &str = r#"temp.report.start: 20231015T000000Z
+temp.report.end: 20231016T000000Z
+reports.grouped.timezone: UTC
+
+[
+{"id":2,"start":"20231015T080000Z","end":"20231015T093000Z","tags":["client:acme","api"]},
+{"id":1,"start":"20231015T110000Z","tags":["docs"]}
+]
+"#;
+
+    /// Renders `INPUT` as `format` with the clock pinned to noon.
+    fn render_pinned(format: &str) -> String {
+        let overrides: Vec<(String, String)> = [
+            ("reports.grouped.now", "20231015T120000Z"),
+            ("reports.grouped.color", "off"),
+            ("reports.grouped.width", "0"),
+            ("reports.grouped.format", format),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        let data = read_data(INPUT.as_bytes(), &overrides).unwrap();
+        let options = Options::from_data(&data).unwrap();
+        colored::control::set_override(options.color);
+        let mut out = vec![];
+        render(&data, &options, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn renders_a_pinned_text_report() {
+        assert_eq!(
+            render_pinned("text"),
+            "2023-10-15 - 2023-10-15\n\
+             \n\
+             \x20           TAGS    MINUTES      HOURS     %\n\
+             client:acme, api         90        1.5    60\n\
+             \x20           docs         60        1.0    40 ▶\n\
+             \x20          TOTAL        150        2.5\n\
+             ▶ running since 11:00 (60 min so far): docs\n\
+             \n\
+             \x20      intervals          2\n"
+        );
+    }
+
+    #[test]
+    fn renders_a_pinned_json_report() {
+        let document: serde_json::Value = serde_json::from_str(&render_pinned("json")).unwrap();
+        assert_eq!(document["schema"], json::SCHEMA);
+        assert_eq!(document["now"], "2023-10-15T12:00:00+00:00");
+        assert_eq!(document["range"]["title"], "2023-10-15 - 2023-10-15");
+        assert_eq!(document["total"]["seconds"], 9000);
+        assert_eq!(document["interval_count"], 2);
+
+        let groups = document["groups"].as_array().unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0]["title"], "client:acme, api");
+        assert_eq!(groups[0]["tags"], serde_json::json!(["client:acme", "api"]));
+        assert_eq!(groups[0]["minutes"], 90);
+        assert_eq!(groups[0]["percent"], 60.0);
+        assert!(groups[0].get("running").is_none());
+        assert_eq!(groups[1]["title"], "docs");
+        assert_eq!(groups[1]["seconds"], 3600);
+        assert_eq!(groups[1]["running"], true);
+
+        assert_eq!(document["running"]["start"], "2023-10-15T11:00:00+00:00");
+        assert_eq!(document["running"]["minutes"], 60);
+    }
+}
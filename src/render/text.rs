@@ -0,0 +1,706 @@
+//! The coloured text table printed by `timew grouped`.
+
+use super::{display_width, pad_string, pad_string_end, truncate, wrap};
+use crate::compare::Change;
+use crate::distribution::{Distribution, DistributionConfig, WEEKDAYS};
+use crate::goal::GoalProgress;
+use crate::input::{Data, Interval};
+use crate::options::{Align, Options, Overflow};
+use crate::pivot::Pivot;
+use crate::quality::Quality;
+use crate::theme::Theme;
+use chrono::{DateTime, FixedOffset};
+use colored::*;
+use std::io::{self, Write};
+
+/// Pads `label` to `len` columns on the side `align` leaves open.
+fn align_label(label: &str, len: usize, align: Align) -> String {
+    match align {
+        Align::Left => pad_string_end(label, len),
+        Align::Right => pad_string(label, len),
+    }
+}
+
+/// Pads the label of a line `depth` levels down a tree to `len` columns,
+/// indented on the side `align` leaves open so the nesting stays visible
+/// when right-aligned too.
+fn indent_label(label: &str, depth: usize, len: usize, align: Align) -> String {
+    let indent = "  ".repeat(depth);
+    let len = len.saturating_sub(indent.len());
+    match align {
+        Align::Left => format!("{}{}", indent, pad_string_end(label, len)),
+        Align::Right => format!("{}{}", pad_string(label, len), indent),
+    }
+}
+
+/// Writes the report as an aligned table, coloured when colour is enabled.
+pub fn render(data: &Data, options: &Options, out: &mut impl Write) -> io::Result<()> {
+    let report = data.report(options);
+    let theme = &options.theme;
+    let pad_label = |label: &str, len: usize| align_label(label, len, options.align);
+    let total_duration = report.total;
+    let bill = options
+        .billing
+        .as_ref()
+        .map(|billing| billing.bill(data, &report, options));
+    let amount_header = match options.billing.as_ref() {
+        Some(billing) if !billing.currency.is_empty() => format!("AMOUNT {}", billing.currency),
+        _ => "AMOUNT".to_string(),
+    };
+    let amount_width = amount_header.len().max(12);
+    let comparison = data.compare(&report, options);
+
+    // Everything right of the title: minutes, hours and percent, then the
+    // optional billing, comparison and running marker columns.
+    let mut other_columns = 1 + 10 + 1 + 10 + 1 + 5;
+    if bill.is_some() {
+        other_columns += 1 + 10 + 1 + amount_width;
+    }
+    if comparison.is_some() {
+        other_columns += 1 + 8 + 1 + 8 + 1 + 7;
+    }
+    if report.lines.iter().any(|line| line.running) {
+        other_columns += 2;
+    }
+    let max_title = title_width(
+        report
+            .lines
+            .iter()
+            .map(|line| 2 * line.depth + display_width(&line.label)),
+        other_columns,
+        options,
+    );
+
+    if options.sections.title {
+        writeln!(out, "{}", theme.muted(&data.report_title()))?;
+        if let Some(previous) = data.previous.as_deref().filter(|_| comparison.is_some()) {
+            writeln!(
+                out,
+                "{}",
+                theme.muted(&format!("compared with {}", previous.report_title()))
+            )?;
+        }
+        writeln!(out)?;
+    }
+
+    if let Some(period) = options.pivot {
+        let mut pivot = data.pivot(period, options);
+        pivot.sort(options.sort, options.reverse);
+        render_pivot(&pivot, options, out)?;
+    } else if options.sections.table {
+        let mut header = format!(
+            "{} {:>10} {:>10} {:>5}",
+            pad_label("TAGS", max_title),
+            "MINUTES",
+            "HOURS",
+            "%"
+        );
+        if bill.is_some() {
+            header.push_str(&format!(
+                " {:>10} {:>width$}",
+                "BILLABLE",
+                amount_header,
+                width = amount_width
+            ));
+        }
+        if comparison.is_some() {
+            header.push_str(&format!(" {:>8} {:>8} {:>7}", "PREV", "DELTA", "DELTA%"));
+        }
+        writeln!(out, "{}", header.bold().underline())?;
+
+        let mut it = report.lines.iter().enumerate().peekable();
+        while let Some((index, report_line)) = it.next() {
+            let (depth, row) = (report_line.depth, &report_line.row);
+            let pad_title = |title: &str| indent_label(title, depth, max_title, options.align);
+            let title_lines = fit_title(
+                &report_line.label,
+                max_title.saturating_sub(2 * depth),
+                options,
+            );
+            let percent = report.percent(&row.duration);
+            let mut line = format!(
+                "{} {:>10} {:10.1} {:5.0}",
+                pad_title(&title_lines[0]),
+                row.duration.num_minutes(),
+                row.duration.num_seconds() as f64 / 3600.0,
+                percent,
+            );
+            if let Some(bill) = &bill {
+                let billed = &bill.lines[index];
+                match billed.excluded {
+                    true => line.push_str(&format!(
+                        " {:>10} {:>width$}",
+                        "-",
+                        "excluded",
+                        width = amount_width
+                    )),
+                    false => line.push_str(&format!(
+                        " {:10.2} {:width$.2}",
+                        billed.billable.num_seconds() as f64 / 3600.0,
+                        billed.amount,
+                        width = amount_width
+                    )),
+                }
+            }
+            let color = theme.row_color(&row.title, &row.tags, percent);
+            let mut string: ColoredString = line.normal();
+            if let Some(color) = color {
+                string = string.color(color);
+            }
+            let last = it.peek().is_none() && title_lines.len() == 1;
+            if options.sections.totals && last {
+                string = string.underline();
+            }
+            let change = match &comparison {
+                Some(comparison) => change_cells(&comparison.lines[index], theme),
+                None => String::new(),
+            };
+            let marker = match report_line.running {
+                true => format!(" {}", theme.accent(RUNNING_MARKER).bold()),
+                false => String::new(),
+            };
+            writeln!(out, "{}{}{}", string, change, marker)?;
+            for (number, title_line) in title_lines.iter().enumerate().skip(1) {
+                let mut string = pad_title(title_line).normal();
+                if let Some(color) = color {
+                    string = string.color(color);
+                }
+                if options.sections.totals && it.peek().is_none() && number + 1 == title_lines.len()
+                {
+                    string = string.underline();
+                }
+                writeln!(out, "{}", string)?;
+            }
+        }
+    }
+
+    if options.sections.totals && options.pivot.is_none() {
+        let mut line = format!(
+            "{} {:>10} {:10.1}",
+            pad_label("TOTAL", max_title),
+            total_duration.num_minutes(),
+            total_duration.num_seconds() as f64 / 3600.0,
+        );
+        if let Some(bill) = &bill {
+            line.push_str(&format!(
+                " {:5} {:10.2} {:width$.2}",
+                "",
+                bill.billable.num_seconds() as f64 / 3600.0,
+                bill.amount,
+                width = amount_width
+            ));
+        }
+        match &comparison {
+            Some(comparison) => {
+                if bill.is_none() {
+                    line.push_str(&format!(" {:5}", ""));
+                }
+                writeln!(
+                    out,
+                    "{}{}",
+                    line.bold(),
+                    change_cells(&comparison.total, theme)
+                )?
+            }
+            None => writeln!(out, "{}", line.bold())?,
+        }
+        if let Some(interval) = data.running_interval() {
+            render_running(data, interval, theme, out)?;
+        }
+        if report.overlapping {
+            writeln!(
+                out,
+                "{}",
+                theme.muted(
+                    "percentages overlap: intervals with several tags count under each of them"
+                )
+            )?;
+        }
+    }
+
+    if options.sections.goals && !options.goals.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{}", theme.muted(&pad_label("goals", max_title)))?;
+        for progress in data.goal_progress(&options.goals, &report) {
+            render_goal(&progress, max_title, options.align, theme, out)?;
+        }
+    }
+
+    if let Some(config) = &options.quality {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{}",
+            theme.muted(&pad_label("data quality", max_title))
+        )?;
+        let quality = data.quality(config);
+        render_quality(&quality, max_title, options.align, theme, out)?;
+    }
+
+    if let Some(config) = &options.distribution {
+        let bar_width = match options.width {
+            Some(width) => width
+                .saturating_sub(max_title + DISTRIBUTION_VALUE_WIDTH)
+                .clamp(10, DISTRIBUTION_BAR_WIDTH),
+            None => DISTRIBUTION_BAR_WIDTH,
+        };
+        let histograms = Histograms {
+            config,
+            max_title,
+            bar_width,
+            pad_label: &pad_label,
+        };
+        histograms.render("", &data.distribution(|_| true), theme, out)?;
+        if config.groups {
+            for (title, distribution) in data.group_distributions(&report, options) {
+                let title = match title.as_str() {
+                    "" => String::from("(untagged)"),
+                    _ => title,
+                };
+                histograms.render(&format!("{}: ", title), &distribution, theme, out)?;
+            }
+        }
+    }
+
+    if options.sections.intervals {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{}",
+            theme.muted(&format!(
+                "{} {:>10}",
+                pad_label("intervals", max_title),
+                data.intervals.len()
+            ))
+        )?;
+    }
+
+    let annotated_intervals: Vec<&Interval> = data
+        .intervals
+        .iter()
+        .filter(|interval| interval.annotation.is_some())
+        .collect();
+    if options.sections.annotations && !annotated_intervals.is_empty() {
+        let range = data.report_range();
+        writeln!(out)?;
+        writeln!(out, "{}", theme.muted(&pad_label("annotations", max_title)))?;
+        for interval in annotated_intervals {
+            let duration = interval.clipped_duration(&range);
+            let string = format!(
+                "{} {:>10} {:10.1} {:5.0} {}",
+                pad_label(&truncate(&interval.title(), max_title), max_title),
+                duration.num_minutes(),
+                duration.num_seconds() as f64 / 3600.0,
+                report.percent(&duration),
+                interval.annotation.as_ref().unwrap(),
+            );
+            writeln!(out, "{}", theme.muted(&string))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The narrowest the title column gets when fitting a table to the width.
+const MIN_TITLE_WIDTH: usize = 10;
+
+/// The width of the title column: wide enough for the widest title and
+/// `options.min_width`, but no wider than `options.width` leaves next to
+/// `other_columns`.
+fn title_width(
+    titles: impl Iterator<Item = usize>,
+    other_columns: usize,
+    options: &Options,
+) -> usize {
+    let widest = titles.chain([options.min_width]).max().unwrap_or(0);
+    match options.width {
+        Some(width) => widest
+            .min(width.saturating_sub(other_columns))
+            .max(MIN_TITLE_WIDTH.min(widest)),
+        None => widest,
+    }
+}
+
+/// `title` fitted to `width` columns: cut off with an ellipsis, or wrapped
+/// over several lines with `overflow=wrap`.
+fn fit_title(title: &str, width: usize, options: &Options) -> Vec<String> {
+    match options.overflow {
+        Overflow::Truncate => vec![truncate(title, width)],
+        Overflow::Wrap => wrap(title, width),
+    }
+}
+
+const RUNNING_MARKER: &str = "▶";
+
+/// Writes when the running interval started and how much of it the report
+/// counts.
+fn render_running(
+    data: &Data,
+    interval: &Interval,
+    theme: &Theme,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let duration = interval.clipped_duration(&data.report_range());
+    let since = match interval.start.date_naive() == data.now.date_naive() {
+        true => interval.start.format("%H:%M").to_string(),
+        false => interval.start.format("%Y-%m-%d %H:%M").to_string(),
+    };
+    let title = match interval.title() {
+        title if title.is_empty() => String::from("(untagged)"),
+        title => title,
+    };
+    writeln!(
+        out,
+        "{} {}",
+        theme.accent(RUNNING_MARKER).bold(),
+        theme.muted(&format!(
+            "running since {} ({} min so far): {}",
+            since,
+            duration.num_minutes(),
+            title
+        ))
+    )
+}
+
+/// The previous hours, the delta in hours and the delta in percent, with
+/// the deltas in the theme's good colour when time went up and its bad
+/// colour when it went down.
+fn change_cells(change: &Change, theme: &Theme) -> String {
+    let hours = |duration: chrono::Duration| duration.num_seconds() as f64 / 3600.0;
+    let percent = match change.percent {
+        Some(percent) => format!("{:+.0}%", percent),
+        None if change.delta.is_zero() => String::from("-"),
+        None => String::from("new"),
+    };
+    let delta = format!("{:+8.1} {:>7}", hours(change.delta), percent);
+    let delta = match change.delta.num_seconds() {
+        0 => delta.normal(),
+        seconds if seconds > 0 => theme.good(&delta),
+        _ => theme.bad(&delta),
+    };
+    format!(" {:8.1} {}", hours(change.previous), delta)
+}
+
+/// Writes one line per overlap, in the theme's bad colour, and per gap, in
+/// its warning colour, with their local times and length.
+fn render_quality(
+    quality: &Quality,
+    max_title: usize,
+    align: Align,
+    theme: &Theme,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let pad = |label: &str| align_label(label, max_title, align);
+    let span = |start: &DateTime<FixedOffset>, end: &DateTime<FixedOffset>| {
+        format!(
+            "{} - {} {:>7} min",
+            start.format("%Y-%m-%d %H:%M"),
+            match start.date_naive() == end.date_naive() {
+                true => end.format("%H:%M").to_string(),
+                false => end.format("%Y-%m-%d %H:%M").to_string(),
+            },
+            (*end - *start).num_minutes()
+        )
+    };
+    if quality.is_clean() {
+        writeln!(out, "{}", theme.good(&pad("no problems")))?;
+    }
+    for overlap in &quality.overlaps {
+        let line = format!(
+            "{} {}  {} | {}",
+            pad("overlap"),
+            span(&overlap.start, &overlap.end),
+            overlap.tags.0.join(", "),
+            overlap.tags.1.join(", ")
+        );
+        writeln!(out, "{}", theme.bad(&line))?;
+    }
+    for gap in &quality.gaps {
+        let line = format!("{} {}", pad("gap"), span(&gap.start, &gap.end));
+        writeln!(out, "{}", theme.warning(&line))?;
+    }
+    Ok(())
+}
+
+/// The widest a distribution bar gets.
+const DISTRIBUTION_BAR_WIDTH: usize = 40;
+
+/// The width of the hours and percentage after a distribution bar.
+const DISTRIBUTION_VALUE_WIDTH: usize = 1 + 1 + 7 + 1 + 5;
+
+/// Writes distributions as horizontal bar histograms aligned with the
+/// table.
+struct Histograms<'a, F: Fn(&str, usize) -> String> {
+    config: &'a DistributionConfig,
+    max_title: usize,
+    bar_width: usize,
+    pad_label: &'a F,
+}
+
+impl<F: Fn(&str, usize) -> String> Histograms<'_, F> {
+    /// Writes the hour-of-day and weekday histograms enabled in the config,
+    /// each headed by its name after `prefix`. Hours outside the first and
+    /// last tracked hour are left out; nothing is written without time.
+    fn render(
+        &self,
+        prefix: &str,
+        distribution: &Distribution,
+        theme: &Theme,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        let total = distribution.total();
+        if total.is_zero() {
+            return Ok(());
+        }
+        if self.config.hours {
+            let first = distribution.hours.iter().position(|hour| !hour.is_zero());
+            let last = distribution.hours.iter().rposition(|hour| !hour.is_zero());
+            let buckets: Vec<(String, chrono::Duration)> = (first.unwrap_or(0)..=last.unwrap_or(0))
+                .map(|hour| (format!("{:02}:00", hour), distribution.hours[hour]))
+                .collect();
+            self.render_histogram(
+                &format!("{}hour of day", prefix),
+                &buckets,
+                total,
+                theme,
+                out,
+            )?;
+        }
+        if self.config.weekdays {
+            let buckets: Vec<(String, chrono::Duration)> = WEEKDAYS
+                .iter()
+                .zip(distribution.weekdays)
+                .map(|(name, duration)| (name.to_string(), duration))
+                .collect();
+            self.render_histogram(&format!("{}weekday", prefix), &buckets, total, theme, out)?;
+        }
+        Ok(())
+    }
+
+    fn render_histogram(
+        &self,
+        heading: &str,
+        buckets: &[(String, chrono::Duration)],
+        total: chrono::Duration,
+        theme: &Theme,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{}",
+            theme.muted(&(self.pad_label)(heading, self.max_title))
+        )?;
+        let longest = buckets
+            .iter()
+            .map(|(_, duration)| duration.num_seconds())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        for (label, duration) in buckets {
+            let eighths =
+                (duration.num_seconds() * (self.bar_width * 8) as i64 + longest / 2) / longest;
+            writeln!(
+                out,
+                "{} {} {:7.1} {:4.0}%",
+                (self.pad_label)(label, self.max_title),
+                pad_string_end(&bar(eighths as usize), self.bar_width),
+                duration.num_seconds() as f64 / 3600.0,
+                duration.num_seconds() as f64 / total.num_seconds() as f64 * 100.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A bar `eighths` eighths of a column long, drawn with block characters.
+fn bar(eighths: usize) -> String {
+    const PARTIAL: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+    format!("{}{}", "█".repeat(eighths / 8), PARTIAL[eighths % 8])
+}
+
+const GOAL_BAR_WIDTH: usize = 20;
+
+/// Writes a goal as `actual / target h`, a progress bar, the percentage and
+/// the time left, in the theme's warning colour while under the target and
+/// its good colour once it is met.
+fn render_goal(
+    progress: &GoalProgress,
+    max_title: usize,
+    align: Align,
+    theme: &Theme,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let hours = |duration: chrono::Duration| duration.num_seconds() as f64 / 3600.0;
+    let filled =
+        ((progress.fraction() * GOAL_BAR_WIDTH as f64).round() as usize).min(GOAL_BAR_WIDTH);
+    let bar = format!(
+        "{}{}",
+        "█".repeat(filled),
+        "░".repeat(GOAL_BAR_WIDTH - filled)
+    );
+    let remaining = match progress.is_met() {
+        true => format!("{:.1} h over", hours(-progress.remaining())),
+        false => format!("{:.1} h left", hours(progress.remaining())),
+    };
+    let title = align_label(&truncate(&progress.title, max_title), max_title, align);
+    let line = format!(
+        "{} {:10.1} {:>10} {:5.0} {} {}",
+        title,
+        hours(progress.actual),
+        format!("/ {:.1}", hours(progress.target)),
+        progress.fraction() * 100.0,
+        bar,
+        remaining
+    );
+    match progress.is_met() {
+        true => writeln!(out, "{}", theme.good(&line)),
+        false => writeln!(out, "{}", theme.warning(&line)),
+    }
+}
+
+fn hours_cell(duration: &chrono::Duration, width: usize) -> String {
+    match duration.is_zero() {
+        true => format!("{:>width$}", "-", width = width),
+        false => format!(
+            "{:>width$.1}",
+            duration.num_seconds() as f64 / 3600.0,
+            width = width
+        ),
+    }
+}
+
+/// Writes a pivot table of hours. When the periods do not fit
+/// `options.width`, they are split over several tables stacked on top of
+/// each other, with the row totals in the last one.
+fn render_pivot(pivot: &Pivot, options: &Options, out: &mut impl Write) -> io::Result<()> {
+    let labels: Vec<String> = pivot
+        .periods
+        .iter()
+        .map(|period| pivot.period.label(*period))
+        .collect();
+    let column_width = labels.iter().map(String::len).chain([6]).max().unwrap_or(0);
+    let total_width = 7;
+    // Leave room for at least one period and the totals.
+    let max_title = title_width(
+        pivot
+            .rows
+            .iter()
+            .map(|row| display_width(&row.title))
+            .chain(["TOTAL".len()]),
+        column_width + 1 + total_width + 1,
+        options,
+    );
+    let per_block = match options.width {
+        Some(width) => {
+            (width.saturating_sub(max_title + total_width + 1) / (column_width + 1)).max(1)
+        }
+        None => labels.len().max(1),
+    };
+    let column_count = labels.len();
+    let blocks: Vec<std::ops::Range<usize>> = (0..column_count.max(1))
+        .step_by(per_block)
+        .map(|start| start..(start + per_block).min(column_count))
+        .collect();
+
+    for (index, columns) in blocks.iter().enumerate() {
+        let last = index + 1 == blocks.len();
+        if index > 0 {
+            writeln!(out)?;
+        }
+        let mut header = align_label("TAGS", max_title, options.align);
+        labels[columns.clone()].iter().for_each(|label| {
+            header.push_str(&format!(" {:>width$}", label, width = column_width));
+        });
+        if last {
+            header.push_str(&format!(" {:>width$}", "TOTAL", width = total_width));
+        }
+        writeln!(out, "{}", header.bold().underline())?;
+
+        let mut it = pivot.rows.iter().peekable();
+        while let Some(row) = it.next() {
+            let title_lines = fit_title(&row.title, max_title, options);
+            let mut line = align_label(&title_lines[0], max_title, options.align);
+            row.cells[columns.clone()].iter().for_each(|cell| {
+                line.push(' ');
+                line.push_str(&hours_cell(cell, column_width));
+            });
+            if last {
+                line.push(' ');
+                line.push_str(&hours_cell(&row.total, total_width));
+            }
+            let mut string = line.normal();
+            let percent = pivot.percent(&row.total);
+            let color = options.theme.row_color(&row.title, &row.tags, percent);
+            if let Some(color) = color {
+                string = string.color(color);
+            }
+            if options.sections.totals && it.peek().is_none() && title_lines.len() == 1 {
+                string = string.underline();
+            }
+            writeln!(out, "{}", string)?;
+            for (number, title_line) in title_lines.iter().enumerate().skip(1) {
+                let mut string = align_label(title_line, max_title, options.align).normal();
+                if let Some(color) = color {
+                    string = string.color(color);
+                }
+                if options.sections.totals && it.peek().is_none() && number + 1 == title_lines.len()
+                {
+                    string = string.underline();
+                }
+                writeln!(out, "{}", string)?;
+            }
+        }
+
+        if options.sections.totals {
+            let mut line = align_label("TOTAL", max_title, options.align);
+            pivot.totals[columns.clone()].iter().for_each(|cell| {
+                line.push(' ');
+                line.push_str(&hours_cell(cell, column_width));
+            });
+            if last {
+                line.push(' ');
+                line.push_str(&hours_cell(&pivot.total, total_width));
+            }
+            writeln!(out, "{}", line.bold())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::test_data;
+
+    /// The label column of the report's rows.
+    fn labels(align: &str) -> Vec<String> {
+        let data = test_data(
+            &[("grouping", "tree"), ("align", align)],
+            &["0800 - 0900 # client:acme", "0900 - 0930 # client:beta"],
+        );
+        let options = Options::from_data(&data).unwrap();
+        let mut out = Vec::new();
+        render(&data, &options, &mut out).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .skip(3)
+            .take(3)
+            .map(|line| line[..12].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_tree_indentation_whatever_the_alignment() {
+        assert_eq!(
+            labels("left"),
+            ["client      ", "  acme      ", "  beta      "]
+        );
+        assert_eq!(
+            labels("right"),
+            ["      client", "      acme  ", "      beta  "]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Colour themes and the rules that colour report rows.
+
+use crate::input::Data;
+use crate::pattern::TagPattern;
+use colored::{Color, ColoredString, Colorize};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// The colours of everything but the rows; `None` leaves text uncoloured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// Met goals, more time than before and a clean quality check.
+    pub good: Option<Color>,
+    /// Less time than before and overlapping intervals.
+    pub bad: Option<Color>,
+    /// Goals not met yet and gaps.
+    pub warning: Option<Color>,
+    /// The running marker.
+    pub accent: Option<Color>,
+    /// The title line and the sections below the table, which are dimmed
+    /// when this is `None`.
+    pub muted: Option<Color>,
+}
+
+impl Palette {
+    /// The palette of a theme by name: `default`, `dark`, `light` or
+    /// `mono`.
+    pub fn named(name: &str) -> Option<Palette> {
+        match name {
+            "default" => Some(Palette {
+                good: Some(Color::Green),
+                bad: Some(Color::Red),
+                warning: Some(Color::Yellow),
+                accent: None,
+                muted: None,
+            }),
+            "dark" => Some(Palette {
+                good: Some(Color::BrightGreen),
+                bad: Some(Color::BrightRed),
+                warning: Some(Color::BrightYellow),
+                accent: Some(Color::BrightCyan),
+                muted: Some(Color::BrightBlack),
+            }),
+            "light" => Some(Palette {
+                good: Some(Color::Green),
+                bad: Some(Color::Red),
+                warning: Some(Color::Magenta),
+                accent: Some(Color::Blue),
+                muted: None,
+            }),
+            "mono" => Some(Palette {
+                good: None,
+                bad: None,
+                warning: None,
+                accent: None,
+                muted: None,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::named("default").unwrap()
+    }
+}
+
+/// What a colour rule matches.
+#[derive(Debug, Clone)]
+pub enum RowMatcher {
+    /// Any of the row's tags.
+    Tag(TagPattern),
+    /// The row's title.
+    Regex(Regex),
+}
+
+/// The colours of the report: a palette and the rules for row colours.
+#[derive(Debug, Clone, Default)]
+pub struct Theme {
+    pub palette: Palette,
+    /// Colours for rows by exact title.
+    pub highlights: HashMap<String, Color>,
+    /// Colours for rows by tag pattern or title regex, in the order they
+    /// are tried.
+    pub rules: Vec<(RowMatcher, Color)>,
+    /// Colours for rows above a share of the total time in percent,
+    /// highest first.
+    pub thresholds: Vec<(f64, Color)>,
+}
+
+impl Theme {
+    /// The colour of a row: its title's highlight, else the most specific
+    /// matching tag rule, else the first matching regex rule, else the
+    /// colour of the highest threshold its `percent` of the total is
+    /// above.
+    pub fn row_color(&self, title: &str, tags: &[String], percent: f64) -> Option<Color> {
+        if let Some(color) = self.highlights.get(title) {
+            return Some(*color);
+        }
+        let tag_rule = self
+            .rules
+            .iter()
+            .filter_map(|(matcher, color)| match matcher {
+                RowMatcher::Tag(pattern) if tags.iter().any(|tag| pattern.matches(tag)) => {
+                    Some((pattern.specificity(), *color))
+                }
+                _ => None,
+            })
+            .max_by_key(|(specificity, _)| *specificity);
+        if let Some((_, color)) = tag_rule {
+            return Some(color);
+        }
+        let regex_rule = self
+            .rules
+            .iter()
+            .find_map(|(matcher, color)| match matcher {
+                RowMatcher::Regex(regex) if regex.is_match(title) => Some(*color),
+                _ => None,
+            });
+        regex_rule.or_else(|| {
+            self.thresholds
+                .iter()
+                .find(|(threshold, _)| percent > *threshold)
+                .map(|(_, color)| *color)
+        })
+    }
+
+    pub fn good(&self, text: &str) -> ColoredString {
+        paint(text, self.palette.good)
+    }
+
+    pub fn bad(&self, text: &str) -> ColoredString {
+        paint(text, self.palette.bad)
+    }
+
+    pub fn warning(&self, text: &str) -> ColoredString {
+        paint(text, self.palette.warning)
+    }
+
+    pub fn accent(&self, text: &str) -> ColoredString {
+        paint(text, self.palette.accent)
+    }
+
+    pub fn muted(&self, text: &str) -> ColoredString {
+        match self.palette.muted {
+            Some(color) => text.color(color),
+            None => text.dimmed(),
+        }
+    }
+}
+
+fn paint(text: &str, color: Option<Color>) -> ColoredString {
+    match color {
+        Some(color) => text.color(color),
+        None => text.normal(),
+    }
+}
+
+/// Whether to colour the output when `color` is not set for the report:
+/// never when `NO_COLOR` is set or timewarrior's own `color` setting is
+/// off. Run by timewarrior, which captures the report, colour follows that
+/// setting; run on its own, it is on only when stdout is a terminal.
+pub fn auto_color(data: &Data) -> bool {
+    use std::io::IsTerminal;
+
+    if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    let extension = data.find_setting("temp.version").is_some();
+    match data.find_setting("color") {
+        Some(color) if !color.value_to_bool() => false,
+        _ if extension => true,
+        _ => std::io::stdout().is_terminal(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::test_data;
+    use crate::options::Options;
+    use colored::Color;
+
+    #[test]
+    fn picks_highlights_then_tag_rules_then_regexes_then_thresholds() {
+        let data = test_data(
+            &[
+                ("highlight.client:acme, api", "magenta"),
+                ("color.tag.client:*", "blue"),
+                ("color.tag.client:acme", "green"),
+                ("color.regex.^do", "yellow"),
+                ("color.above.10", "cyan"),
+                ("color.above.50%", "red"),
+            ],
+            &[],
+        );
+        let theme = Options::from_data(&data).unwrap().theme;
+        let tags =
+            |tags: &[&str]| -> Vec<String> { tags.iter().map(|tag| tag.to_string()).collect() };
+        let color = |title: &str, percent: f64| {
+            theme.row_color(
+                title,
+                &tags(&title.split(", ").collect::<Vec<_>>()),
+                percent,
+            )
+        };
+        assert_eq!(color("client:acme, api", 5.0), Some(Color::Magenta));
+        assert_eq!(color("client:acme, docs", 5.0), Some(Color::Green));
+        assert_eq!(color("client:beta", 5.0), Some(Color::Blue));
+        assert_eq!(color("docs", 60.0), Some(Color::Yellow));
+        assert_eq!(color("code", 60.0), Some(Color::Red));
+        assert_eq!(color("code", 20.0), Some(Color::Cyan));
+        assert_eq!(color("code", 10.0), None);
+    }
+}
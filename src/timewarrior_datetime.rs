@@ -0,0 +1,46 @@
+//! Parsing of timewarrior's `20231015T080000Z` timestamps, usable with
+//! `#[serde(with = "...")]`.
+
+use crate::timezone::ReportTimezone;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, ParseResult};
+use serde::{self, Deserialize, Deserializer};
+
+const FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Parses a timestamp into the system's local zone.
+pub fn parse(s: &str) -> ParseResult<DateTime<FixedOffset>> {
+    parse_in(s, &ReportTimezone::Local)
+}
+
+/// Parses a timestamp into `timezone`.
+pub fn parse_in(s: &str, timezone: &ReportTimezone) -> ParseResult<DateTime<FixedOffset>> {
+    let dt = NaiveDateTime::parse_from_str(s, FORMAT)?;
+    Ok(timezone.localize(&dt))
+}
+
+/// Formats `datetime` as a UTC timestamp.
+pub fn format(datetime: &DateTime<FixedOffset>) -> String {
+    datetime.naive_utc().format(FORMAT).to_string()
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<FixedOffset>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse(&s)
+        .map_err(|error| serde::de::Error::custom(format!("invalid timestamp '{}': {}", s, error)))
+}
+
+/// The same for optional timestamps such as an interval's `end`.
+pub mod option {
+    use chrono::{DateTime, FixedOffset};
+    use serde::Deserializer;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<FixedOffset>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        super::deserialize(deserializer).map(Some)
+    }
+}
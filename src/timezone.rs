@@ -0,0 +1,113 @@
+//! The timezone reports are shown in.
+
+use chrono::{DateTime, FixedOffset, Local, LocalResult, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+/// The zone timewarrior's UTC timestamps are converted to, either the
+/// system's local zone or an IANA zone set with `reports.grouped.timezone`.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ReportTimezone {
+    #[default]
+    Local,
+    Named(Tz),
+}
+
+impl ReportTimezone {
+    /// Parses `local` (or an empty string) or an IANA name such as
+    /// `Europe/Berlin`.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim() {
+            "" | "local" => Ok(ReportTimezone::Local),
+            name => name
+                .parse::<Tz>()
+                .map(ReportTimezone::Named)
+                .map_err(|_| format!("unknown timezone '{}'", name)),
+        }
+    }
+
+    /// Converts a UTC timestamp using the offset in effect at that instant,
+    /// so DST changes inside the report range are honoured.
+    pub fn localize(&self, utc: &NaiveDateTime) -> DateTime<FixedOffset> {
+        match self {
+            ReportTimezone::Local => Local.from_utc_datetime(utc).fixed_offset(),
+            ReportTimezone::Named(tz) => tz.from_utc_datetime(utc).fixed_offset(),
+        }
+    }
+
+    /// Re-expresses `datetime` in this zone.
+    pub fn convert(&self, datetime: &DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        self.localize(&datetime.naive_utc())
+    }
+
+    /// The instant a wall-clock time in this zone refers to. Ambiguous times
+    /// resolve to the earlier instant; times skipped by a DST change move
+    /// forward to the first valid time after them.
+    pub fn from_local(&self, local: &NaiveDateTime) -> DateTime<FixedOffset> {
+        let result = match self {
+            ReportTimezone::Local => fixed(Local.from_local_datetime(local)),
+            ReportTimezone::Named(tz) => fixed(tz.from_local_datetime(local)),
+        };
+        match result {
+            Some(datetime) => datetime,
+            None => self.from_local(&(*local + chrono::Duration::minutes(15))),
+        }
+    }
+}
+
+fn fixed<Tz: TimeZone>(result: LocalResult<DateTime<Tz>>) -> Option<DateTime<FixedOffset>> {
+    match result {
+        LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => {
+            Some(datetime.fixed_offset())
+        }
+        LocalResult::None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn berlin() -> ReportTimezone {
+        ReportTimezone::parse("Europe/Berlin").unwrap()
+    }
+
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M").unwrap()
+    }
+
+    #[test]
+    fn localizes_with_the_offset_at_each_instant() {
+        // Clocks went back from 03:00 CEST to 02:00 CET at 01:00 UTC.
+        let before = berlin().localize(&time("2023-10-29T00:30"));
+        let after = berlin().localize(&time("2023-10-29T01:30"));
+        assert_eq!(before.to_rfc3339(), "2023-10-29T02:30:00+02:00");
+        assert_eq!(after.to_rfc3339(), "2023-10-29T02:30:00+01:00");
+        assert_eq!(after - before, chrono::Duration::hours(1));
+    }
+
+    #[test]
+    fn resolves_repeated_local_times_to_the_earlier_instant() {
+        let instant = berlin().from_local(&time("2023-10-29T02:30"));
+        assert_eq!(instant.to_rfc3339(), "2023-10-29T02:30:00+02:00");
+        assert_eq!(berlin().convert(&instant), instant);
+    }
+
+    #[test]
+    fn moves_skipped_local_times_forward() {
+        // Clocks went forward from 02:00 CET to 03:00 CEST.
+        let instant = berlin().from_local(&time("2023-03-26T02:30"));
+        assert_eq!(instant.to_rfc3339(), "2023-03-26T03:00:00+02:00");
+        let midnight = berlin().from_local(&time("2023-03-26T00:00"));
+        let next = berlin().from_local(&time("2023-03-27T00:00"));
+        assert_eq!(next - midnight, chrono::Duration::hours(23));
+    }
+
+    #[test]
+    fn parses_local_and_named_zones() {
+        assert!(matches!(
+            ReportTimezone::parse(" local "),
+            Ok(ReportTimezone::Local)
+        ));
+        assert!(ReportTimezone::parse("Mars/Olympus").is_err());
+    }
+}